@@ -0,0 +1,377 @@
+use crate::ai::backend::{AiRequest, Backend, Completion};
+use crate::ai::error::AiError;
+use crate::ai::stream::SseDecoder;
+use crate::ai::usage::Usage;
+use crate::config::AidoConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Backend that calls the Anthropic Messages API directly
+pub struct AnthropicBackend {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    system: &'a str,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize, Default)]
+struct ApiUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    delta: Option<StreamDelta>,
+    #[serde(default)]
+    error: Option<ErrorDetail>,
+    /// Sent with `message_start` (input tokens)
+    #[serde(default)]
+    message: Option<StreamMessage>,
+    /// Sent with `message_delta` (output tokens so far)
+    #[serde(default)]
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    #[serde(default)]
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl AnthropicBackend {
+    pub fn new(base_url: &str, api_key: &str, model: &str, max_tokens: u32) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            max_tokens,
+        }
+    }
+
+    pub fn from_config(config: &AidoConfig) -> Result<Self> {
+        let key_env = &config.ai.api_key_env;
+        let api_key = std::env::var(key_env)
+            .ok()
+            .filter(|key| !key.trim().is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "API backend selected but ${} is not set.\n\
+                    Export your Anthropic API key or switch to `backend = \"cli\"`.",
+                    key_env
+                )
+            })?;
+
+        Ok(Self::new(
+            &config.ai.api_base_url,
+            &api_key,
+            &config.ai.model,
+            config.ai.max_tokens,
+        ))
+    }
+
+    fn model_for<'a>(&'a self, request: &'a AiRequest) -> &'a str {
+        request.model.as_deref().unwrap_or(&self.model)
+    }
+
+    async fn send(&self, request: &AiRequest, stream: bool) -> Result<reqwest::Response> {
+        let body = MessagesRequest {
+            model: self.model_for(request),
+            max_tokens: self.max_tokens,
+            system: &request.system,
+            messages: conversation(request),
+            stream,
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach the Anthropic API")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .map(|e| e.error.message)
+                .unwrap_or(text);
+            return Err(AiError::Request {
+                status: Some(status.as_u16()),
+                message: format!("Anthropic API request failed ({}): {}", status, message),
+            }
+            .into());
+        }
+
+        Ok(response)
+    }
+}
+
+/// History turns followed by the current user message
+fn conversation(request: &AiRequest) -> Vec<Message<'_>> {
+    let mut messages = Vec::with_capacity(request.history.len() * 2 + 1);
+    for turn in &request.history {
+        messages.push(Message {
+            role: "user",
+            content: &turn.user,
+        });
+        messages.push(Message {
+            role: "assistant",
+            content: &turn.assistant,
+        });
+    }
+    messages.push(Message {
+        role: "user",
+        content: &request.user,
+    });
+    messages
+}
+
+#[async_trait]
+impl Backend for AnthropicBackend {
+    fn name(&self) -> &str {
+        "api"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+        let text = self
+            .send(request, false)
+            .await?
+            .text()
+            .await
+            .context("Failed to read Anthropic API response")?;
+
+        let parsed: MessagesResponse =
+            serde_json::from_str(&text).context("Unexpected Anthropic API response format")?;
+
+        let usage = parsed.usage.map(|u| {
+            Usage::new(u.input_tokens, u.output_tokens).priced_for(self.model_for(request))
+        });
+
+        let answer: String = parsed
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect();
+
+        Ok(Completion::new(answer.trim(), usage))
+    }
+
+    async fn stream(
+        &self,
+        request: &AiRequest,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Completion> {
+        let mut response = self.send(request, true).await?;
+        let mut decoder = SseDecoder::new();
+        let mut answer = String::new();
+        let mut usage = Usage::default();
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Anthropic API stream interrupted")?
+        {
+            for data in decoder.push(&chunk) {
+                let event: StreamEvent =
+                    serde_json::from_str(&data).context("Unexpected Anthropic API stream event")?;
+
+                match event.kind.as_str() {
+                    "message_start" => {
+                        if let Some(u) = event.message.and_then(|m| m.usage) {
+                            usage.input_tokens = u.input_tokens;
+                        }
+                    }
+                    "message_delta" => {
+                        if let Some(u) = event.usage {
+                            usage.output_tokens = u.output_tokens;
+                        }
+                    }
+                    "content_block_delta" => {
+                        if let Some(text) = event.delta.and_then(|d| d.text) {
+                            on_text(&text);
+                            answer.push_str(&text);
+                        }
+                    }
+                    "message_stop" => {
+                        let usage = usage.priced_for(self.model_for(request));
+                        return Ok(Completion::new(answer.trim(), Some(usage)));
+                    }
+                    "error" => {
+                        let message = event
+                            .error
+                            .map(|e| e.message)
+                            .unwrap_or_else(|| "unknown error".to_string());
+                        return Err(AiError::Request {
+                            status: None,
+                            message: format!("Anthropic API stream failed: {}", message),
+                        }
+                        .into());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        anyhow::bail!("Anthropic API stream ended unexpectedly")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::mock_server::serve_once;
+
+    #[tokio::test]
+    async fn test_complete_against_mock_server() {
+        let (base_url, request) = serve_once(
+            200,
+            "application/json",
+            r#"{"content":[{"type":"text","text":"ls -la\n"}],"stop_reason":"end_turn","usage":{"input_tokens":1000,"output_tokens":100}}"#,
+        )
+        .await;
+
+        let backend = AnthropicBackend::new(&base_url, "test-key", "claude-test", 256);
+        let answer = backend
+            .complete(&AiRequest::new("system rules", "User request: list files"))
+            .await
+            .unwrap();
+
+        assert_eq!(answer.text, "ls -la");
+        let usage = answer.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (1000, 100));
+        assert!(usage.cost_usd.is_none(), "claude-test has no list price");
+
+        let raw = request.await.unwrap();
+        assert!(raw.starts_with("POST /v1/messages"));
+        assert!(raw.contains("x-api-key: test-key"));
+        assert!(raw.contains(r#""system":"system rules""#));
+        assert!(raw.contains(r#""model":"claude-test""#));
+    }
+
+    #[tokio::test]
+    async fn test_stream_emits_deltas() {
+        let body = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" world\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":2}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+        let (base_url, request) = serve_once(200, "text/event-stream", body).await;
+
+        let backend = AnthropicBackend::new(&base_url, "test-key", "claude-test", 256);
+        let mut chunks = Vec::new();
+        let answer = backend
+            .stream(&AiRequest::new("", "hi"), &mut |text| {
+                chunks.push(text.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["Hello", " world"]);
+        assert_eq!(answer.text, "Hello world");
+        assert_eq!(answer.usage, Some(Usage::new(12, 2)));
+        assert!(request.await.unwrap().contains(r#""stream":true"#));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_an_error() {
+        let body = "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n";
+        let (base_url, _request) = serve_once(200, "text/event-stream", body).await;
+
+        let backend = AnthropicBackend::new(&base_url, "test-key", "claude-test", 256);
+        let result = backend.stream(&AiRequest::new("", "hi"), &mut |_| {}).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_surfaced() {
+        let (base_url, _request) = serve_once(
+            401,
+            "application/json",
+            r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+        )
+        .await;
+
+        let backend = AnthropicBackend::new(&base_url, "bad-key", "claude-test", 256);
+        let err = backend
+            .complete(&AiRequest::new("", "hi"))
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("invalid x-api-key"));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::ai::anthropic::AnthropicBackend;
+use crate::ai::cli_backend::CliBackend;
+use crate::ai::openai::OpenAiBackend;
+use crate::ai::usage::Usage;
+use crate::config::{AidoConfig, BackendKind};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// One earlier exchange in a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub user: String,
+    pub assistant: String,
+}
+
+/// A single prompt sent to a backend
+#[derive(Debug, Clone, Default)]
+pub struct AiRequest {
+    /// Instructions for the model; may be empty
+    pub system: String,
+    /// Earlier turns of the conversation, oldest first
+    pub history: Vec<Turn>,
+    /// The user's turn
+    pub user: String,
+    /// Overrides the backend's configured model (used by the fallback chain)
+    pub model: Option<String>,
+}
+
+impl AiRequest {
+    pub fn new(system: impl Into<String>, user: impl Into<String>) -> Self {
+        Self {
+            system: system.into(),
+            history: Vec::new(),
+            user: user.into(),
+            model: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<Turn>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Flatten system, history and user parts into one prompt for backends without roles
+    pub fn flatten(&self) -> String {
+        let mut prompt = String::new();
+
+        if !self.system.is_empty() {
+            prompt.push_str(&self.system);
+            prompt.push_str("\n\n");
+        }
+
+        if !self.history.is_empty() {
+            prompt.push_str("Previous conversation:\n");
+            for turn in &self.history {
+                prompt.push_str(&format!(
+                    "User: {}\nAssistant: {}\n\n",
+                    turn.user, turn.assistant
+                ));
+            }
+            prompt.push_str("Continue the conversation.\n\n");
+        }
+
+        prompt.push_str(&self.user);
+        prompt
+    }
+}
+
+/// A backend's answer plus the usage it reported
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub usage: Option<Usage>,
+}
+
+impl Completion {
+    pub fn new(text: impl Into<String>, usage: Option<Usage>) -> Self {
+        Self {
+            text: text.into(),
+            usage,
+        }
+    }
+}
+
+/// Something that can turn a prompt into a model response
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Short identifier shown in diagnostics
+    fn name(&self) -> &str;
+
+    /// Model used when the request doesn't name one
+    fn model(&self) -> &str;
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion>;
+
+    /// Stream the response, handing each text fragment to `on_text` as it arrives.
+    /// Returns the full text. Backends without streaming support emit it in one piece.
+    async fn stream(
+        &self,
+        request: &AiRequest,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Completion> {
+        let completion = self.complete(request).await?;
+        on_text(&completion.text);
+        Ok(completion)
+    }
+}
+
+/// Build the backend selected by `[ai] backend`
+pub fn from_config(config: &AidoConfig) -> Result<Box<dyn Backend>> {
+    match config.ai.backend {
+        BackendKind::Cli => Ok(Box::new(CliBackend::from_config(config)?)),
+        BackendKind::Api => Ok(Box::new(AnthropicBackend::from_config(config)?)),
+        BackendKind::OpenAi => Ok(Box::new(OpenAiBackend::from_config(config)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_matches_cli_prompt_layout() {
+        let request = AiRequest::new("You are helpful.", "Question: hi");
+        assert_eq!(request.flatten(), "You are helpful.\n\nQuestion: hi");
+
+        let bare = AiRequest::new("", "Explain ls");
+        assert_eq!(bare.flatten(), "Explain ls");
+    }
+
+    #[test]
+    fn test_flatten_includes_history() {
+        let request =
+            AiRequest::new("Rules.", "Question: and recursively?").with_history(vec![Turn {
+                user: "Question: how do I list files?".to_string(),
+                assistant: "Use `ls`.".to_string(),
+            }]);
+
+        assert_eq!(
+            request.flatten(),
+            "Rules.\n\nPrevious conversation:\nUser: Question: how do I list files?\nAssistant: Use `ls`.\n\nContinue the conversation.\n\nQuestion: and recursively?"
+        );
+    }
+}
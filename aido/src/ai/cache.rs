@@ -0,0 +1,198 @@
+use crate::config::{data_dir, write_private, AidoConfig};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// On-disk cache of model responses, one JSON file per key
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl_secs: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: u64,
+    backend: String,
+    model: String,
+    response: String,
+}
+
+/// Everything that can change the model's answer
+pub struct CacheKey<'a> {
+    pub backend: &'a str,
+    pub model: &'a str,
+    pub system: &'a str,
+    pub context: &'a str,
+    pub prompt: &'a str,
+}
+
+impl CacheKey<'_> {
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.backend,
+            self.model,
+            self.system,
+            self.context,
+            self.prompt,
+        ] {
+            // Length-prefix each part so ("ab", "c") and ("a", "bc") differ
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl_secs: u64) -> Self {
+        Self { dir, ttl_secs }
+    }
+
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(data_dir()?.join("cache"))
+    }
+
+    /// The configured cache, or `None` when caching is turned off
+    pub fn from_config(config: &AidoConfig) -> Result<Option<Self>> {
+        if !config.cache.enabled {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(Self::default_dir()?, config.cache.ttl_secs)))
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<String> {
+        let path = self.entry_path(key);
+        let contents = fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+
+        if now().saturating_sub(entry.created_at) >= self.ttl_secs {
+            fs::remove_file(&path).ok();
+            return None;
+        }
+
+        Some(entry.response)
+    }
+
+    pub fn put(&self, key: &CacheKey, response: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory: {}", self.dir.display()))?;
+
+        let entry = CacheEntry {
+            created_at: now(),
+            backend: key.backend.to_string(),
+            model: key.model.to_string(),
+            response: response.to_string(),
+        };
+
+        // Entries hold commands exactly as the model wrote them, secrets included
+        let path = self.entry_path(key);
+        write_private(&path, &serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to write cache entry: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Remove every cached entry, returning how many were deleted
+    pub fn clear(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read cache directory: {}", self.dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.digest()))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tempfile::TempDir;
+
+    fn key(prompt: &str) -> CacheKey<'_> {
+        CacheKey {
+            backend: "cli",
+            model: "claude-test",
+            system: "system",
+            context: "Working directory: /tmp",
+            prompt,
+        }
+    }
+
+    fn temp_cache(ttl_secs: u64) -> (TempDir, ResponseCache) {
+        let dir = temp_dir();
+        let cache = ResponseCache::new(dir.path().to_path_buf(), ttl_secs);
+        (dir, cache)
+    }
+
+    #[test]
+    fn test_digest_depends_on_every_part() {
+        assert_eq!(key("ls").digest(), key("ls").digest());
+        assert_ne!(key("ls").digest(), key("pwd").digest());
+
+        let mut other_model = key("ls");
+        other_model.model = "other";
+        assert_ne!(key("ls").digest(), other_model.digest());
+    }
+
+    #[test]
+    fn test_round_trip_and_clear() {
+        let (_dir, cache) = temp_cache(60);
+
+        assert_eq!(cache.get(&key("ls")), None);
+        cache.put(&key("ls"), "ls -la").unwrap();
+        assert_eq!(cache.get(&key("ls")).as_deref(), Some("ls -la"));
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.get(&key("ls")), None);
+    }
+
+    #[test]
+    fn test_expired_entries_are_ignored() {
+        let (_dir, cache) = temp_cache(0);
+
+        cache.put(&key("ls"), "ls -la").unwrap();
+        assert_eq!(cache.get(&key("ls")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_entries_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, cache) = temp_cache(60);
+        cache.put(&key("ls"), "ls -la").unwrap();
+
+        let mode = fs::metadata(cache.entry_path(&key("ls")))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::ai::error::AiError;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+lazy_static! {
+    static ref CANCEL: Notify = Notify::new();
+}
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static LISTENER: Once = Once::new();
+
+/// Installing a SIGINT handler replaces the default "terminate" behaviour for the
+/// whole process, so the listener only cancels while an AI call is running and
+/// exits like an unhandled Ctrl+C otherwise.
+fn ensure_listener() {
+    LISTENER.call_once(|| {
+        tokio::spawn(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if IN_FLIGHT.load(Ordering::SeqCst) > 0 {
+                    CANCEL.notify_waiters();
+                } else {
+                    std::process::exit(130);
+                }
+            }
+        });
+    });
+}
+
+struct InFlight;
+
+impl InFlight {
+    fn enter() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Run an AI call with an optional time limit, aborting it on Ctrl+C.
+///
+/// Aborting drops the future, which kills any child process spawned with
+/// `kill_on_drop(true)`.
+pub async fn run_cancellable<T>(
+    timeout: Option<Duration>,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    ensure_listener();
+    let _in_flight = InFlight::enter();
+    let cancelled = CANCEL.notified();
+
+    let limited = async {
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, call)
+                .await
+                .unwrap_or_else(|_| Err(AiError::Timeout(limit.as_secs()).into())),
+            None => call.await,
+        }
+    };
+
+    tokio::select! {
+        result = limited => result,
+        _ = cancelled => Err(AiError::Cancelled.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_times_out() {
+        let result: Result<()> = run_cancellable(Some(Duration::from_millis(20)), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+
+        assert!(AiError::is_timeout(&result.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_passes_through_result() {
+        let result = run_cancellable(None, async { Ok(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+}
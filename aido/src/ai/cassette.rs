@@ -0,0 +1,320 @@
+use crate::ai::backend::{AiRequest, Backend, Completion, Turn};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Recorded prompt/response pairs, stored as pretty JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Backend and model the cassette was recorded with
+    pub backend: String,
+    pub model: String,
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// It embeds the working directory, so replay only compares its `prompt_kind`
+    pub system: String,
+    #[serde(default)]
+    pub history: Vec<Turn>,
+    pub user: String,
+    pub model: String,
+    pub response: String,
+}
+
+impl Interaction {
+    fn matches(&self, request: &AiRequest) -> bool {
+        self.user == request.user
+            && self.history == request.history
+            && prompt_kind(&self.system) == prompt_kind(&request.system)
+    }
+}
+
+/// The parts of a system prompt that don't depend on the machine: the template's
+/// first line, plus the sections `SystemPrompts` appends for `--investigate` and
+/// `--alternatives`. A plain `do` and an `--alternatives` run of the same request
+/// differ here even though their user messages are identical.
+fn prompt_kind(system: &str) -> Vec<&str> {
+    let mut lines = system.lines();
+    lines
+        .next()
+        .into_iter()
+        .chain(
+            lines.filter(|line| {
+                line.starts_with("Investigation:") || line.starts_with("Alternatives:")
+            }),
+        )
+        .collect()
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse cassette: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write cassette: {}", path.display()))
+    }
+}
+
+/// Wraps a real backend and appends every successful exchange to a cassette file
+pub struct RecordingBackend {
+    inner: Box<dyn Backend>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingBackend {
+    /// Start a fresh cassette at `path`, replacing any existing file
+    pub fn new(inner: Box<dyn Backend>, path: &Path) -> Result<Self> {
+        let cassette = Cassette {
+            backend: inner.name().to_string(),
+            model: inner.model().to_string(),
+            interactions: Vec::new(),
+        };
+        cassette.save(path)?;
+
+        Ok(Self {
+            inner,
+            path: path.to_path_buf(),
+            cassette: Mutex::new(cassette),
+        })
+    }
+
+    /// Written after every exchange so an interrupted run still leaves a usable file
+    fn record(&self, request: &AiRequest, response: &str) -> Result<()> {
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction {
+            system: request.system.clone(),
+            history: request.history.clone(),
+            user: request.user.clone(),
+            model: request
+                .model
+                .clone()
+                .unwrap_or_else(|| self.inner.model().to_string()),
+            response: response.to_string(),
+        });
+        cassette.save(&self.path)
+    }
+}
+
+#[async_trait]
+impl Backend for RecordingBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+        let completion = self.inner.complete(request).await?;
+        self.record(request, &completion.text)?;
+        Ok(completion)
+    }
+
+    async fn stream(
+        &self,
+        request: &AiRequest,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Completion> {
+        let completion = self.inner.stream(request, on_text).await?;
+        self.record(request, &completion.text)?;
+        Ok(completion)
+    }
+}
+
+/// Serves responses from a cassette instead of calling a model.
+///
+/// Requests are matched on the user message, conversation history and kind of
+/// system prompt (see `prompt_kind`). When the same prompt was recorded several
+/// times the responses are served in order, and the last one repeats once they
+/// run out. Unmatched prompts are an error.
+pub struct ReplayBackend {
+    path: PathBuf,
+    cassette: Cassette,
+    served: Mutex<Vec<bool>>,
+}
+
+impl ReplayBackend {
+    pub fn load(path: &Path) -> Result<Self> {
+        let cassette = Cassette::load(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            served: Mutex::new(vec![false; cassette.interactions.len()]),
+            cassette,
+        })
+    }
+
+    fn lookup(&self, request: &AiRequest) -> Result<&Interaction> {
+        let mut served = self.served.lock().unwrap();
+        let matching: Vec<usize> = self
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.matches(request))
+            .map(|(i, _)| i)
+            .collect();
+
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&i| !served[i])
+            .or_else(|| matching.last().copied())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No response recorded in {} for prompt:\n{}",
+                    self.path.display(),
+                    request.user
+                )
+            })?;
+
+        served[index] = true;
+        Ok(&self.cassette.interactions[index])
+    }
+}
+
+#[async_trait]
+impl Backend for ReplayBackend {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn model(&self) -> &str {
+        &self.cassette.model
+    }
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+        let interaction = self.lookup(request)?;
+        Ok(Completion::new(interaction.response.clone(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    /// Answers every prompt with a numbered echo
+    struct EchoBackend {
+        calls: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl Backend for EchoBackend {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn model(&self) -> &str {
+            "echo-model"
+        }
+
+        async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            Ok(Completion::new(
+                format!("{} #{}", request.user, calls),
+                None,
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = temp_dir();
+        let path = dir.path().join("cassette.json");
+        let recorder = RecordingBackend::new(
+            Box::new(EchoBackend {
+                calls: Mutex::new(0),
+            }),
+            &path,
+        )
+        .unwrap();
+
+        let list = AiRequest::new(
+            "You are an expert.\ncwd: /home/me",
+            "User request: list files",
+        );
+        recorder.complete(&list).await.unwrap();
+        recorder.complete(&list).await.unwrap();
+
+        // A different working directory in the system prompt still matches
+        let replay = ReplayBackend::load(&path).unwrap();
+        let moved = AiRequest::new(
+            "You are an expert.\ncwd: /elsewhere",
+            "User request: list files",
+        );
+        assert_eq!(replay.model(), "echo-model");
+        assert_eq!(
+            replay.complete(&moved).await.unwrap().text,
+            "User request: list files #1"
+        );
+        assert_eq!(
+            replay.complete(&moved).await.unwrap().text,
+            "User request: list files #2"
+        );
+        assert_eq!(
+            replay.complete(&moved).await.unwrap().text,
+            "User request: list files #2"
+        );
+
+        // The same request with alternatives asked for is a different prompt
+        let alternatives = AiRequest::new(
+            "You are an expert.\ncwd: /home/me\n\nAlternatives: offer 3 approaches",
+            "User request: list files",
+        );
+        assert!(replay.complete(&alternatives).await.is_err());
+
+        let err = replay
+            .complete(&AiRequest::new("", "User request: delete files"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No response recorded"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_history() {
+        let dir = temp_dir();
+        let path = dir.path().join("cassette.json");
+        let turn = Turn {
+            user: "Question: what is ls?".to_string(),
+            assistant: "It lists files.".to_string(),
+        };
+        Cassette {
+            backend: "cli".to_string(),
+            model: "m".to_string(),
+            interactions: vec![Interaction {
+                system: String::new(),
+                history: vec![turn.clone()],
+                user: "Question: and hidden ones?".to_string(),
+                model: "m".to_string(),
+                response: "Use ls -a.".to_string(),
+            }],
+        }
+        .save(&path)
+        .unwrap();
+
+        let replay = ReplayBackend::load(&path).unwrap();
+        let follow_up = AiRequest::new("", "Question: and hidden ones?");
+        assert!(replay.complete(&follow_up).await.is_err());
+        assert_eq!(
+            replay
+                .complete(&follow_up.with_history(vec![turn]))
+                .await
+                .unwrap()
+                .text,
+            "Use ls -a."
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use crate::ai::backend::{AiRequest, Backend, Completion};
+use crate::ai::error::AiError;
+use crate::ai::stream::{parse_cli_stream_line, CliStreamEvent};
+use crate::config::AidoConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::process::{Command, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+/// Backend that shells out to the Claude Code CLI (`claude -p`)
+pub struct CliBackend {
+    model: String,
+    claude_command: String,
+}
+
+impl CliBackend {
+    pub fn from_config(config: &AidoConfig) -> Result<Self> {
+        // Check if claude CLI is available
+        let claude_cmd = config.ai.claude_command.clone();
+
+        let check = Command::new(&claude_cmd)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        if check.is_err() || !check.unwrap().success() {
+            anyhow::bail!(
+                "Claude CLI not found. Please install Claude Code CLI first.\n\
+                Visit: https://docs.claude.com/claude-code"
+            );
+        }
+
+        Ok(Self {
+            model: config.ai.model.clone(),
+            claude_command: claude_cmd,
+        })
+    }
+
+    async fn call_claude(&self, prompt: &str, model: &str) -> Result<Completion> {
+        // kill_on_drop lets a timeout or Ctrl+C abort the call without leaving
+        // an orphaned claude process behind.
+        let output = tokio::process::Command::new(&self.claude_command)
+            .arg("-p")
+            .arg(prompt)
+            .arg("--model")
+            .arg(model)
+            .args(["--output-format", "json"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("Failed to execute claude command")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AiError::Request {
+                status: None,
+                message: format!("Claude command failed: {}", stderr),
+            }
+            .into());
+        }
+
+        let stdout =
+            String::from_utf8(output.stdout).context("Claude output is not valid UTF-8")?;
+
+        // The JSON result carries token usage and cost; older CLIs print plain text
+        match parse_cli_stream_line(stdout.trim()) {
+            CliStreamEvent::Result {
+                text,
+                is_error: true,
+                ..
+            } => Err(AiError::Request {
+                status: None,
+                message: format!("Claude command failed: {}", text),
+            }
+            .into()),
+            CliStreamEvent::Result { text, usage, .. } => Ok(Completion::new(text.trim(), usage)),
+            _ => Ok(Completion::new(stdout.trim(), None)),
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for CliBackend {
+    fn name(&self) -> &str {
+        "cli"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+        let model = request.model.as_deref().unwrap_or(&self.model);
+        self.call_claude(&request.flatten(), model).await
+    }
+
+    async fn stream(
+        &self,
+        request: &AiRequest,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Completion> {
+        let mut child = tokio::process::Command::new(&self.claude_command)
+            .arg("-p")
+            .arg(request.flatten())
+            .arg("--model")
+            .arg(request.model.as_deref().unwrap_or(&self.model))
+            .args([
+                "--output-format",
+                "stream-json",
+                "--verbose",
+                "--include-partial-messages",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to execute claude command")?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .context("Failed to capture claude output")?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        // Drain stderr alongside stdout; a full stderr pipe would block the CLI
+        // while we wait for more stdout
+        let mut stderr = child
+            .stderr
+            .take()
+            .context("Failed to capture claude errors")?;
+        let stderr = tokio::spawn(async move {
+            let mut text = String::new();
+            stderr.read_to_string(&mut text).await.map(|_| text)
+        });
+
+        let mut streamed = false;
+        let mut result = None;
+
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .context("Failed to read claude output")?
+        {
+            match parse_cli_stream_line(&line) {
+                CliStreamEvent::Delta(text) => {
+                    streamed = true;
+                    on_text(&text);
+                }
+                // Whole messages repeat the deltas; only render them if the CLI
+                // didn't send partial messages.
+                CliStreamEvent::Message(text) if !streamed => {
+                    streamed = true;
+                    on_text(&text);
+                }
+                CliStreamEvent::Result {
+                    text,
+                    is_error,
+                    usage,
+                } => {
+                    if is_error {
+                        return Err(AiError::Request {
+                            status: None,
+                            message: format!("Claude command failed: {}", text),
+                        }
+                        .into());
+                    }
+                    result = Some((text, usage));
+                }
+                _ => {}
+            }
+        }
+
+        let status = child
+            .wait()
+            .await
+            .context("Failed to wait for claude command")?;
+
+        if !status.success() {
+            let stderr = stderr.await.ok().and_then(Result::ok).unwrap_or_default();
+            return Err(AiError::Request {
+                status: None,
+                message: format!("Claude command failed: {}", stderr),
+            }
+            .into());
+        }
+
+        let (text, usage) = result.context("Claude stream ended before a result was received")?;
+        if !streamed {
+            on_text(&text);
+        }
+
+        Ok(Completion::new(text.trim(), usage))
+    }
+}
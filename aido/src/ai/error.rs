@@ -0,0 +1,181 @@
+use thiserror::Error;
+
+/// Failures the modes handle specially instead of just printing
+#[derive(Debug, Error)]
+pub enum AiError {
+    #[error("AI request timed out after {0}s (increase ai.timeout_secs to wait longer)")]
+    Timeout(u64),
+
+    #[error("AI request cancelled")]
+    Cancelled,
+
+    #[error(
+        "Daily budget of ${budget} reached (${spent:.4} spent today).\n\
+        Raise or remove ai.daily_budget to keep going, or try again tomorrow."
+    )]
+    BudgetExceeded { spent: f64, budget: f64 },
+
+    /// The backend answered with an error; `status` is set for HTTP backends
+    #[error("{message}")]
+    Request {
+        status: Option<u16>,
+        message: String,
+    },
+}
+
+/// How the client should react to a failed call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Transient (rate limit, overload, network): retry, then try the next model
+    Retryable,
+    /// This model can't serve the request: skip straight to the next model
+    ModelUnavailable,
+    /// Retrying won't help (bad credentials, cancelled, timed out, ...)
+    Fatal,
+}
+
+impl AiError {
+    pub fn is_cancelled(err: &anyhow::Error) -> bool {
+        matches!(err.downcast_ref::<AiError>(), Some(AiError::Cancelled))
+    }
+
+    pub fn is_timeout(err: &anyhow::Error) -> bool {
+        matches!(err.downcast_ref::<AiError>(), Some(AiError::Timeout(_)))
+    }
+
+    pub fn is_budget_exceeded(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<AiError>(),
+            Some(AiError::BudgetExceeded { .. })
+        )
+    }
+
+    pub fn classify(err: &anyhow::Error) -> ErrorClass {
+        match err.downcast_ref::<AiError>() {
+            Some(AiError::Timeout(_))
+            | Some(AiError::Cancelled)
+            | Some(AiError::BudgetExceeded { .. }) => ErrorClass::Fatal,
+            Some(AiError::Request { status, message }) => classify_request(*status, message),
+            None => {
+                let network = err.chain().any(|cause| {
+                    cause
+                        .downcast_ref::<reqwest::Error>()
+                        .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+                });
+                if network {
+                    ErrorClass::Retryable
+                } else {
+                    ErrorClass::Fatal
+                }
+            }
+        }
+    }
+}
+
+fn classify_request(status: Option<u16>, message: &str) -> ErrorClass {
+    match status {
+        Some(408 | 409 | 429 | 500 | 502 | 503 | 504 | 529) => return ErrorClass::Retryable,
+        Some(404) => return ErrorClass::ModelUnavailable,
+        Some(_) => {}
+        None => {}
+    }
+
+    // The CLI only gives us its error text
+    let message = message.to_lowercase();
+    let retryable = [
+        "rate limit",
+        "rate_limit",
+        "overloaded",
+        "429",
+        "529",
+        "503",
+        "502",
+        "internal server error",
+        "econnreset",
+        "etimedout",
+        "network",
+        "connection",
+    ];
+    let unavailable = [
+        "not_found_error",
+        "model not found",
+        "invalid model",
+        "unknown model",
+        "does not exist",
+    ];
+
+    if unavailable.iter().any(|p| message.contains(p)) {
+        ErrorClass::ModelUnavailable
+    } else if status.is_none() && retryable.iter().any(|p| message.contains(p)) {
+        ErrorClass::Retryable
+    } else {
+        ErrorClass::Fatal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(status: Option<u16>, message: &str) -> anyhow::Error {
+        AiError::Request {
+            status,
+            message: message.to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_classify_http_status() {
+        assert_eq!(
+            AiError::classify(&request(Some(429), "slow down")),
+            ErrorClass::Retryable
+        );
+        assert_eq!(
+            AiError::classify(&request(Some(529), "Overloaded")),
+            ErrorClass::Retryable
+        );
+        assert_eq!(
+            AiError::classify(&request(Some(404), "model: foo")),
+            ErrorClass::ModelUnavailable
+        );
+        assert_eq!(
+            AiError::classify(&request(Some(401), "invalid x-api-key")),
+            ErrorClass::Fatal
+        );
+    }
+
+    #[test]
+    fn test_classify_cli_messages() {
+        assert_eq!(
+            AiError::classify(&request(
+                None,
+                "Claude command failed: API Error: 529 Overloaded"
+            )),
+            ErrorClass::Retryable
+        );
+        assert_eq!(
+            AiError::classify(&request(
+                None,
+                "Claude command failed: model not found: foo"
+            )),
+            ErrorClass::ModelUnavailable
+        );
+        assert_eq!(
+            AiError::classify(&request(None, "Claude command failed: Invalid API key")),
+            ErrorClass::Fatal
+        );
+    }
+
+    #[test]
+    fn test_timeouts_and_cancellation_are_final() {
+        assert_eq!(
+            AiError::classify(&AiError::Timeout(5).into()),
+            ErrorClass::Fatal
+        );
+        assert_eq!(
+            AiError::classify(&AiError::Cancelled.into()),
+            ErrorClass::Fatal
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Larger files are truncated so they can't crowd out the request itself
+pub const MAX_INSTRUCTIONS_BYTES: usize = 8 * 1024;
+
+/// Checked in each directory, in this order
+const CANDIDATES: [&str; 2] = ["AIDO.md", ".aido/instructions.md"];
+
+/// Conventions for the current project, read from `AIDO.md` or `.aido/instructions.md`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectInstructions {
+    pub path: PathBuf,
+    pub text: String,
+    /// Set when the file was longer than `MAX_INSTRUCTIONS_BYTES`
+    pub truncated: bool,
+}
+
+impl ProjectInstructions {
+    /// Look for an instruction file in the working directory and its parents
+    pub fn discover() -> Result<Option<Self>> {
+        let cwd = std::env::current_dir().context("Failed to read the working directory")?;
+        match find(&cwd) {
+            Some(path) => Self::load(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read project instructions: {}", path.display()))?;
+        let text = text.trim();
+
+        let truncated = text.len() > MAX_INSTRUCTIONS_BYTES;
+        let text = if truncated {
+            let mut end = MAX_INSTRUCTIONS_BYTES;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            &text[..end]
+        } else {
+            text
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            text: text.to_string(),
+            truncated,
+        })
+    }
+}
+
+/// The nearest instruction file between `start` and the root of its git repository.
+/// Outside a repository only `start` itself is checked.
+pub fn find(start: &Path) -> Option<PathBuf> {
+    let stop = repo_root(start).unwrap_or_else(|| start.to_path_buf());
+
+    for dir in start.ancestors() {
+        for candidate in CANDIDATES {
+            let path = dir.join(candidate);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+        if dir == stop {
+            break;
+        }
+    }
+
+    None
+}
+
+fn repo_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tempfile::TempDir;
+
+    fn temp_tree() -> TempDir {
+        let root = temp_dir();
+        fs::create_dir_all(root.path().join("repo/.git")).unwrap();
+        fs::create_dir_all(root.path().join("repo/src/deep")).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_finds_nearest_file_up_to_repo_root() {
+        let tree = temp_tree();
+        let root = tree.path();
+        let deep = root.join("repo/src/deep");
+
+        // Above the repository root: ignored
+        fs::write(root.join("AIDO.md"), "outside").unwrap();
+        assert_eq!(find(&deep), None);
+
+        fs::create_dir_all(root.join("repo/.aido")).unwrap();
+        fs::write(root.join("repo/.aido/instructions.md"), "use just").unwrap();
+        assert_eq!(find(&deep), Some(root.join("repo/.aido/instructions.md")));
+
+        fs::write(root.join("repo/src/AIDO.md"), "src rules").unwrap();
+        assert_eq!(find(&deep), Some(root.join("repo/src/AIDO.md")));
+    }
+
+    #[test]
+    fn test_large_files_are_truncated() {
+        let tree = temp_tree();
+        let path = tree.path().join("repo/AIDO.md");
+        fs::write(&path, "é".repeat(MAX_INSTRUCTIONS_BYTES)).unwrap();
+
+        let instructions = ProjectInstructions::load(&path).unwrap();
+        assert!(instructions.truncated);
+        assert!(instructions.text.len() <= MAX_INSTRUCTIONS_BYTES);
+    }
+}
@@ -1,6 +1,23 @@
+pub mod anthropic;
+pub mod backend;
+pub mod cache;
+pub mod cancel;
+pub mod cassette;
+pub mod cli_backend;
 pub mod client;
+pub mod error;
+pub mod instructions;
+#[cfg(test)]
+mod mock_server;
+pub mod openai;
 pub mod parser;
+pub mod plan;
 pub mod prompts;
+pub mod redact;
+pub mod session;
+pub mod stream;
+pub mod usage;
 
-pub use client::ClaudeClient;
-pub use parser::extract_commands;
+pub use client::{ClaudeClient, ClientOptions};
+pub use error::AiError;
+pub use plan::{Alternative, Plan, PlanStep, ProbeRequest, RiskHint};
@@ -0,0 +1,374 @@
+use crate::ai::backend::{AiRequest, Backend, Completion};
+use crate::ai::error::AiError;
+use crate::ai::stream::SseDecoder;
+use crate::ai::usage::Usage;
+use crate::config::AidoConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Backend for self-hosted servers speaking the OpenAI `/v1/chat/completions` protocol
+/// (llama.cpp server, vLLM, Ollama, ...)
+pub struct OpenAiBackend {
+    http: reqwest::Client,
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    max_tokens: u32,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl From<ChatUsage> for Usage {
+    fn from(usage: ChatUsage) -> Self {
+        Usage::new(usage.prompt_tokens, usage.completion_tokens)
+    }
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChunkResponse {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    /// Only sent (on the last chunk) by servers that support `stream_options.include_usage`
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl OpenAiBackend {
+    pub fn new(base_url: &str, api_key: Option<&str>, model: &str, max_tokens: u32) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: chat_completions_url(base_url),
+            api_key: api_key
+                .filter(|key| !key.trim().is_empty())
+                .map(str::to_string),
+            model: model.to_string(),
+            max_tokens,
+        }
+    }
+
+    pub fn from_config(config: &AidoConfig) -> Result<Self> {
+        if config.ai.openai_model.trim().is_empty() {
+            anyhow::bail!(
+                "OpenAI-compatible backend selected but `ai.openai_model` is empty.\n\
+                Set it to the model name your server exposes."
+            );
+        }
+
+        let env_key = std::env::var(&config.ai.openai_api_key_env).ok();
+        Ok(Self::new(
+            &config.ai.openai_base_url,
+            env_key
+                .as_deref()
+                .filter(|key| !key.trim().is_empty())
+                .or(config.ai.openai_api_key.as_deref()),
+            &config.ai.openai_model,
+            config.ai.max_tokens,
+        ))
+    }
+
+    async fn send(&self, request: &AiRequest, stream: bool) -> Result<reqwest::Response> {
+        let mut messages = Vec::new();
+        if !request.system.is_empty() {
+            messages.push(ChatMessage {
+                role: "system",
+                content: &request.system,
+            });
+        }
+        for turn in &request.history {
+            messages.push(ChatMessage {
+                role: "user",
+                content: &turn.user,
+            });
+            messages.push(ChatMessage {
+                role: "assistant",
+                content: &turn.assistant,
+            });
+        }
+        messages.push(ChatMessage {
+            role: "user",
+            content: &request.user,
+        });
+
+        let body = ChatRequest {
+            model: request.model.as_deref().unwrap_or(&self.model),
+            max_tokens: self.max_tokens,
+            messages,
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+        };
+
+        let mut http_request = self.http.post(&self.endpoint).json(&body);
+        if let Some(key) = &self.api_key {
+            http_request = http_request.bearer_auth(key);
+        }
+
+        let response = http_request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.endpoint))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .map(|e| e.error.message)
+                .unwrap_or(text);
+            return Err(AiError::Request {
+                status: Some(status.as_u16()),
+                message: format!("Chat completion request failed ({}): {}", status, message),
+            }
+            .into());
+        }
+
+        Ok(response)
+    }
+}
+
+/// Accept base URLs with or without the trailing `/v1`
+fn chat_completions_url(base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    if base.ends_with("/v1") {
+        format!("{}/chat/completions", base)
+    } else {
+        format!("{}/v1/chat/completions", base)
+    }
+}
+
+#[async_trait]
+impl Backend for OpenAiBackend {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: &AiRequest) -> Result<Completion> {
+        let text = self
+            .send(request, false)
+            .await?
+            .text()
+            .await
+            .context("Failed to read chat completion response")?;
+
+        let parsed: ChatResponse =
+            serde_json::from_str(&text).context("Unexpected chat completion response format")?;
+
+        let answer = parsed
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        Ok(Completion::new(
+            answer.trim(),
+            parsed.usage.map(Usage::from),
+        ))
+    }
+
+    async fn stream(
+        &self,
+        request: &AiRequest,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Completion> {
+        let mut response = self.send(request, true).await?;
+        let mut decoder = SseDecoder::new();
+        let mut answer = String::new();
+        let mut usage = None;
+        let mut finished = false;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Chat completion stream interrupted")?
+        {
+            for data in decoder.push(&chunk) {
+                if data.trim() == "[DONE]" {
+                    return Ok(Completion::new(answer.trim(), usage));
+                }
+
+                let parsed: ChunkResponse = serde_json::from_str(&data)
+                    .context("Unexpected chat completion stream chunk")?;
+
+                for choice in parsed.choices {
+                    if let Some(text) = choice.delta.content {
+                        on_text(&text);
+                        answer.push_str(&text);
+                    }
+                    finished |= choice.finish_reason.is_some();
+                }
+                if let Some(chunk_usage) = parsed.usage {
+                    usage = Some(chunk_usage.into());
+                }
+            }
+        }
+
+        // Some servers close the connection without sending [DONE]
+        if finished {
+            Ok(Completion::new(answer.trim(), usage))
+        } else {
+            anyhow::bail!("Chat completion stream ended unexpectedly")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::mock_server::serve_once;
+
+    #[test]
+    fn test_chat_completions_url() {
+        assert_eq!(
+            chat_completions_url("http://localhost:8080"),
+            "http://localhost:8080/v1/chat/completions"
+        );
+        assert_eq!(
+            chat_completions_url("http://localhost:11434/v1/"),
+            "http://localhost:11434/v1/chat/completions"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_system_message_and_token() {
+        let (base_url, request) = serve_once(
+            200,
+            "application/json",
+            r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"du -sh *"}}],"usage":{"prompt_tokens":30,"completion_tokens":4}}"#,
+        )
+        .await;
+
+        let backend = OpenAiBackend::new(&base_url, Some("secret"), "llama3", 256);
+        let answer = backend
+            .complete(&AiRequest::new("do rules", "User request: disk usage"))
+            .await
+            .unwrap();
+
+        assert_eq!(answer.text, "du -sh *");
+        assert_eq!(answer.usage, Some(Usage::new(30, 4)));
+
+        let raw = request.await.unwrap();
+        assert!(raw.starts_with("POST /v1/chat/completions"));
+        assert!(raw.to_lowercase().contains("authorization: bearer secret"));
+        assert!(raw.contains(r#"{"role":"system","content":"do rules"}"#));
+        assert!(raw.contains(r#""model":"llama3""#));
+    }
+
+    #[tokio::test]
+    async fn test_stream_emits_deltas() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"git \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"status\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let (base_url, _request) = serve_once(200, "text/event-stream", body).await;
+
+        let backend = OpenAiBackend::new(&base_url, None, "llama3", 256);
+        let mut chunks = Vec::new();
+        let answer = backend
+            .stream(&AiRequest::new("", "status"), &mut |text| {
+                chunks.push(text.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["git ", "status"]);
+        assert_eq!(answer.text, "git status");
+    }
+
+    #[tokio::test]
+    async fn test_complete_without_token() {
+        let (base_url, request) = serve_once(
+            200,
+            "application/json",
+            r#"{"choices":[{"message":{"content":"pwd"}}]}"#,
+        )
+        .await;
+
+        let backend = OpenAiBackend::new(&base_url, None, "llama3", 256);
+        backend
+            .complete(&AiRequest::new("", "where am I"))
+            .await
+            .unwrap();
+
+        let raw = request.await.unwrap();
+        assert!(!raw.to_lowercase().contains("authorization:"));
+        assert!(!raw.contains(r#""role":"system""#));
+    }
+}
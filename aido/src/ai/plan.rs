@@ -0,0 +1,233 @@
+use crate::ai::parser::extract_commands;
+use serde::{Deserialize, Serialize};
+
+/// The model's own estimate of how risky a step is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskHint {
+    Low,
+    Medium,
+    High,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub command: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub risk_hint: RiskHint,
+    #[serde(default)]
+    pub needs_tty: bool,
+}
+
+impl PlanStep {
+    pub fn from_command(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            description: String::new(),
+            risk_hint: RiskHint::Unknown,
+            needs_tty: false,
+        }
+    }
+}
+
+/// Ordered steps generated for a DO request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+    /// Set when the response wasn't valid JSON and commands were guessed from text
+    #[serde(skip)]
+    pub heuristic: bool,
+}
+
+impl Plan {
+    /// Parse the JSON plan contract, falling back to the text extractor when the
+    /// model ignored it.
+    pub fn from_response(response: &str) -> Self {
+        if let Some(plan) = Self::parse_json(response) {
+            return plan;
+        }
+
+        tracing::debug!("Response is not a JSON plan, falling back to command extraction");
+        Self {
+            steps: extract_commands(response)
+                .into_iter()
+                .map(PlanStep::from_command)
+                .collect(),
+            heuristic: true,
+        }
+    }
+
+    pub fn parse_json(response: &str) -> Option<Self> {
+        let json = json_object(response)?;
+        Self::normalized(serde_json::from_str(json).ok()?)
+    }
+
+    /// Trimmed, without blank commands; `None` if no steps are left
+    fn normalized(mut plan: Plan) -> Option<Self> {
+        plan.steps.retain(|step| !step.command.trim().is_empty());
+        for step in &mut plan.steps {
+            step.command = step.command.trim().to_string();
+            step.description = step.description.trim().to_string();
+        }
+
+        if plan.steps.is_empty() {
+            None
+        } else {
+            Some(plan)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// One of several approaches offered for `aido do --alternatives`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Alternative {
+    /// One line on when to prefer this approach
+    #[serde(default)]
+    pub tradeoff: String,
+    #[serde(flatten)]
+    pub plan: Plan,
+}
+
+impl Alternative {
+    /// Every alternative with at least one command, or `None` when the response
+    /// isn't an alternatives object
+    pub fn parse_all(response: &str) -> Option<Vec<Self>> {
+        #[derive(Deserialize)]
+        struct Alternatives {
+            alternatives: Vec<Alternative>,
+        }
+
+        let json = json_object(response)?;
+        let Alternatives { alternatives } = serde_json::from_str(json).ok()?;
+        let alternatives: Vec<Self> = alternatives
+            .into_iter()
+            .filter_map(|mut alternative| {
+                alternative.tradeoff = alternative.tradeoff.trim().to_string();
+                alternative.plan = Plan::normalized(alternative.plan)?;
+                Some(alternative)
+            })
+            .collect();
+
+        (!alternatives.is_empty()).then_some(alternatives)
+    }
+}
+
+/// A request to run read-only commands before planning (`aido do --investigate`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProbeRequest {
+    pub probes: Vec<String>,
+}
+
+impl ProbeRequest {
+    /// `None` when the response is a plan (or anything else) rather than probes
+    pub fn parse(response: &str) -> Option<Self> {
+        let json = json_object(response)?;
+        let mut request: ProbeRequest = serde_json::from_str(json).ok()?;
+
+        request.probes = request
+            .probes
+            .into_iter()
+            .map(|probe| probe.trim().to_string())
+            .filter(|probe| !probe.is_empty())
+            .collect();
+
+        (!request.probes.is_empty()).then_some(request)
+    }
+}
+
+/// Slice out the outermost `{...}`, tolerating markdown fences or chatter around it
+fn json_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    (end > start).then(|| &text[start..=end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_plan() {
+        let response = r#"{"steps":[
+            {"command":"git log --since='1 week ago' --oneline","description":"Show last week's commits","risk_hint":"low","needs_tty":false},
+            {"command":"htop","description":"Open process viewer","risk_hint":"low","needs_tty":true}
+        ]}"#;
+
+        let plan = Plan::from_response(response);
+        assert!(!plan.heuristic);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].description, "Show last week's commits");
+        assert_eq!(plan.steps[0].risk_hint, RiskHint::Low);
+        assert!(plan.steps[1].needs_tty);
+    }
+
+    #[test]
+    fn test_parse_fenced_plan_with_unknown_risk() {
+        let response =
+            "```json\n{\"steps\":[{\"command\":\"rm -r build\",\"risk_hint\":\"moderate\"}]}\n```";
+
+        let plan = Plan::from_response(response);
+        assert!(!plan.heuristic);
+        assert_eq!(plan.steps[0].command, "rm -r build");
+        assert_eq!(plan.steps[0].risk_hint, RiskHint::Unknown);
+        assert!(!plan.steps[0].needs_tty);
+    }
+
+    #[test]
+    fn test_malformed_json_falls_back_to_extractor() {
+        let plan = Plan::from_response("ls -la\npwd");
+        assert!(plan.heuristic);
+        assert_eq!(
+            plan.steps,
+            vec![
+                PlanStep::from_command("ls -la"),
+                PlanStep::from_command("pwd")
+            ]
+        );
+
+        let plan = Plan::from_response("{\"steps\": [ {\"command\": ");
+        assert!(plan.heuristic);
+    }
+
+    #[test]
+    fn test_probe_requests_are_told_apart_from_plans() {
+        let probes =
+            ProbeRequest::parse("{\"probes\":[\"ls -la /var/log\",\" du -sh /var/log/* \",\"\"]}")
+                .unwrap();
+        assert_eq!(probes.probes, ["ls -la /var/log", "du -sh /var/log/*"]);
+
+        let plan = r#"{"steps":[{"command":"rm /var/log/big.log"}]}"#;
+        assert_eq!(ProbeRequest::parse(plan), None);
+        assert_eq!(ProbeRequest::parse("{\"probes\":[]}"), None);
+    }
+
+    #[test]
+    fn test_parse_alternatives() {
+        let response = r#"{"alternatives":[
+            {"tradeoff":" Fast, but follows symlinks ","steps":[{"command":"du -sh *","risk_hint":"low"}]},
+            {"tradeoff":"No commands","steps":[{"command":"  "}]},
+            {"steps":[{"command":"ncdu","needs_tty":true}]}
+        ]}"#;
+
+        let alternatives = Alternative::parse_all(response).unwrap();
+        assert_eq!(alternatives.len(), 2);
+        assert_eq!(alternatives[0].tradeoff, "Fast, but follows symlinks");
+        assert_eq!(alternatives[0].plan.steps[0].command, "du -sh *");
+        assert_eq!(alternatives[1].tradeoff, "");
+        assert!(alternatives[1].plan.steps[0].needs_tty);
+
+        assert_eq!(
+            Alternative::parse_all(r#"{"steps":[{"command":"ls"}]}"#),
+            None
+        );
+    }
+}
@@ -1,41 +1,44 @@
+use crate::ai::instructions::ProjectInstructions;
+use crate::config::config_dir;
+use anyhow::Result;
 use std::env;
+use std::fs;
+use std::path::PathBuf;
 
-pub struct SystemPrompts;
-
-impl SystemPrompts {
-    pub fn do_mode(context: &str) -> String {
-        format!(
-            r#"You are a command-line expert assistant. Your task is to generate shell commands based on user requests.
+const DEFAULT_DO_TEMPLATE: &str = r#"You are a command-line expert assistant. Your task is to generate shell commands based on user requests.
 
 CRITICAL RULES:
-1. Reply ONLY with executable shell commands
-2. NO explanations, NO markdown, NO comments
-3. If multiple commands are needed, put each on a separate line
+1. Reply ONLY with a single JSON object matching the schema below
+2. NO markdown fences, NO text before or after the JSON
+3. If multiple commands are needed, use one step per command, in execution order
 4. Commands should be safe and follow best practices
 5. Use the most common and portable commands when possible
 
 System Context:
 {context}
 
-Output format:
-- Single command: just the command
-- Multiple commands: one per line, in execution order
+Output schema:
+{
+  "steps": [
+    {
+      "command": "<executable shell command>",
+      "description": "<one short sentence saying what the step does>",
+      "risk_hint": "low" | "medium" | "high",
+      "needs_tty": <true if the command is interactive (editors, pagers, prompts, TUIs), otherwise false>
+    }
+  ]
+}
 
 Examples:
 User: "find all Python files modified today"
-You: find . -name "*.py" -mtime -1
+You: {"steps":[{"command":"find . -name \"*.py\" -mtime -1","description":"List Python files modified in the last day","risk_hint":"low","needs_tty":false}]}
 
 User: "create a React component called Button"
-You: mkdir -p components/Button
-echo "import React from 'react';" > components/Button/Button.tsx
-echo "export const Button = () => <button>Click me</button>;" >> components/Button/Button.tsx
+You: {"steps":[{"command":"mkdir -p components/Button","description":"Create the component directory","risk_hint":"low","needs_tty":false},{"command":"printf '%s\\n' \"export const Button = () => <button>Click me</button>;\" > components/Button/Button.tsx","description":"Write the Button component","risk_hint":"medium","needs_tty":false}]}
 
-Now generate the command(s) for the user's request."#
-        )
-    }
+Now generate the plan for the user's request."#;
 
-    pub fn ask_mode() -> String {
-        r#"You are a helpful command-line and programming assistant. Answer the user's questions concisely and accurately.
+const DEFAULT_ASK_TEMPLATE: &str = r#"You are a helpful command-line and programming assistant. Answer the user's questions concisely and accurately.
 
 Guidelines:
 1. Provide clear, accurate information
@@ -44,55 +47,270 @@ Guidelines:
 4. Focus on practical advice
 5. If showing commands, use code blocks for clarity
 
-Keep your answers helpful and to the point."#.to_string()
-    }
+System Context:
+{context}
 
-    pub fn explain_command(command: &str) -> String {
-        format!(
-            r#"Explain the following shell command in detail. Break down each part and explain what it does.
+Keep your answers helpful and to the point."#;
+
+const DEFAULT_EXPLAIN_TEMPLATE: &str = r#"Explain the following shell command in detail. Break down each part and explain what it does.
 
-Command: {}
+Command: {prompt}
 
 Provide:
 1. Overall purpose
 2. Breakdown of each part
 3. Any potential risks or side effects
-4. Alternative approaches if applicable"#,
-            command
-        )
-    }
+4. Alternative approaches if applicable"#;
+
+/// Added to the DO prompt for `aido do --investigate`
+const INVESTIGATE_INSTRUCTIONS: &str = r#"Investigation: before planning, you may look around with read-only commands. Instead of a plan, reply with
+{"probes": ["<read-only command>", ...]}
+and their output will be sent back to you. Only ls, du, df, cat, head, tail, wc, stat, file, find, grep, rg, tree, pwd, which, whoami, uname, ps, sort, uniq, cut and read-only git subcommands are allowed, optionally joined with pipes; no redirection, chaining or command substitution. Ask for at most 5 probes at a time, and only when the answer depends on what is actually on this machine. You can investigate for up to {rounds} round(s); then reply with the plan."#;
+
+/// Added to the DO prompt for `aido do --alternatives N`
+const ALTERNATIVES_INSTRUCTIONS: &str = r#"Alternatives: instead of a single plan, offer {count} genuinely different approaches (different tools or strategies, not small variations of one command). Reply with
+{"alternatives": [{"tradeoff": "<one line on when to prefer this approach>", "steps": [<steps as in the schema above>]}, ...]}"#;
+
+/// A prompt that can be overridden by `<config dir>/prompts/<name>.tmpl`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PromptKind {
+    Do,
+    Ask,
+    Explain,
+}
 
-    pub fn build_context() -> String {
-        let mut context = String::new();
+impl PromptKind {
+    pub const ALL: [PromptKind; 3] = [PromptKind::Do, PromptKind::Ask, PromptKind::Explain];
 
-        // Current directory
-        if let Ok(cwd) = env::current_dir() {
-            context.push_str(&format!("Working directory: {}\n", cwd.display()));
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptKind::Do => "do",
+            PromptKind::Ask => "ask",
+            PromptKind::Explain => "explain",
         }
+    }
 
-        // Shell type
-        if let Ok(shell) = env::var("SHELL") {
-            context.push_str(&format!("Shell: {}\n", shell));
+    pub fn default_template(self) -> &'static str {
+        match self {
+            PromptKind::Do => DEFAULT_DO_TEMPLATE,
+            PromptKind::Ask => DEFAULT_ASK_TEMPLATE,
+            PromptKind::Explain => DEFAULT_EXPLAIN_TEMPLATE,
         }
+    }
 
-        // OS
-        context.push_str(&format!("OS: {}\n", std::env::consts::OS));
+    pub fn override_path(self) -> Result<PathBuf> {
+        Ok(config_dir()?
+            .join("prompts")
+            .join(format!("{}.tmpl", self.name())))
+    }
 
-        // Git branch (if in a git repo)
-        if let Ok(output) = std::process::Command::new("git")
-            .args(["branch", "--show-current"])
-            .output()
-        {
-            if output.status.success() {
-                if let Ok(branch) = String::from_utf8(output.stdout) {
-                    let branch = branch.trim();
-                    if !branch.is_empty() {
-                        context.push_str(&format!("Git branch: {}\n", branch));
-                    }
+    /// The user's template if there is one, otherwise the built-in default
+    pub fn template(self) -> String {
+        let Ok(path) = self.override_path() else {
+            return self.default_template().to_string();
+        };
+
+        match fs::read_to_string(&path) {
+            // Editors add a trailing newline; the built-ins don't have one
+            Ok(template) => template.trim_end().to_string(),
+            Err(e) => {
+                if path.exists() {
+                    tracing::warn!("Ignoring unreadable template {}: {e}", path.display());
                 }
+                self.default_template().to_string()
             }
         }
+    }
+}
+
+/// Values substituted for `{context}`, `{shell}`, `{os}` and `{prompt}`
+pub struct PromptVars<'a> {
+    pub context: &'a str,
+    pub prompt: &'a str,
+}
+
+/// Fill in the known placeholders in one pass; any other braces (e.g. JSON
+/// examples) are left alone, and substituted text is never re-scanned.
+pub fn render(template: &str, vars: &PromptVars) -> String {
+    let shell = shell_name();
+    let placeholders = [
+        ("{context}", vars.context),
+        ("{shell}", shell.as_str()),
+        ("{os}", env::consts::OS),
+        ("{prompt}", vars.prompt),
+    ];
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                rendered.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn shell_name() -> String {
+    env::var("SHELL")
+        .ok()
+        .and_then(|shell| shell.rsplit('/').next().map(str::to_string))
+        .unwrap_or_else(|| "sh".to_string())
+}
+
+pub struct SystemPrompts;
+
+impl SystemPrompts {
+    pub fn do_mode(context: &str, prompt: &str) -> String {
+        render(&PromptKind::Do.template(), &PromptVars { context, prompt })
+    }
+
+    pub fn ask_mode(context: &str, question: &str) -> String {
+        render(
+            &PromptKind::Ask.template(),
+            &PromptVars {
+                context,
+                prompt: question,
+            },
+        )
+    }
+
+    pub fn explain_command(context: &str, command: &str) -> String {
+        render(
+            &PromptKind::Explain.template(),
+            &PromptVars {
+                context,
+                prompt: command,
+            },
+        )
+    }
+
+    /// Append the probe protocol for `--investigate`, allowing `rounds` rounds of probes
+    pub fn with_investigation(system: String, rounds: u32) -> String {
+        format!(
+            "{}\n\n{}",
+            system,
+            INVESTIGATE_INSTRUCTIONS.replace("{rounds}", &rounds.to_string())
+        )
+    }
+
+    /// Ask for `count` alternative plans instead of one
+    pub fn with_alternatives(system: String, count: u32) -> String {
+        format!(
+            "{}\n\n{}",
+            system,
+            ALTERNATIVES_INSTRUCTIONS.replace("{count}", &count.to_string())
+        )
+    }
+
+    /// Append the project's instruction file (AIDO.md) to a system prompt
+    pub fn with_project_instructions(
+        system: String,
+        instructions: Option<&ProjectInstructions>,
+    ) -> String {
+        let Some(instructions) = instructions else {
+            return system;
+        };
+
+        format!(
+            "{}\n\nProject instructions (from {}). Follow them unless the user says otherwise:\n{}",
+            system,
+            instructions.path.display(),
+            instructions.text
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Plan;
+
+    #[test]
+    fn test_do_mode_examples_match_plan_schema() {
+        let prompt = render(
+            PromptKind::Do.default_template(),
+            &PromptVars {
+                context: "OS: linux",
+                prompt: "list files",
+            },
+        );
+        let examples: Vec<&str> = prompt
+            .lines()
+            .filter_map(|line| line.strip_prefix("You: "))
+            .collect();
+
+        assert_eq!(examples.len(), 2);
+        for example in examples {
+            assert!(
+                Plan::parse_json(example).is_some(),
+                "bad example: {example}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_does_not_rescan_substituted_text() {
+        let rendered = render(
+            "{context} / {prompt}",
+            &PromptVars {
+                context: "dir {prompt}",
+                prompt: "ls",
+            },
+        );
+        assert_eq!(rendered, "dir {prompt} / ls");
+    }
+
+    #[test]
+    fn test_project_instructions_are_appended() {
+        let instructions = ProjectInstructions {
+            path: PathBuf::from("/repo/AIDO.md"),
+            text: "Use `just`, not `make`.".to_string(),
+            truncated: false,
+        };
+
+        let system =
+            SystemPrompts::with_project_instructions("Rules.".to_string(), Some(&instructions));
+        assert!(system.starts_with("Rules.\n\nProject instructions (from /repo/AIDO.md)"));
+        assert!(system.ends_with("Use `just`, not `make`."));
+
+        assert_eq!(
+            SystemPrompts::with_project_instructions("Rules.".to_string(), None),
+            "Rules."
+        );
+    }
+
+    #[test]
+    fn test_render_replaces_only_known_placeholders() {
+        let template =
+            "Use {os}. Context:\n{context}\nTask: {prompt}\nReply {\"steps\": []} {unknown}";
+        let rendered = render(
+            template,
+            &PromptVars {
+                context: "Working directory: /tmp",
+                prompt: "list files",
+            },
+        );
 
-        context
+        assert_eq!(
+            rendered,
+            format!(
+                "Use {}. Context:\nWorking directory: /tmp\nTask: list files\nReply {{\"steps\": []}} {{unknown}}",
+                env::consts::OS
+            )
+        );
     }
 }
@@ -0,0 +1,296 @@
+use crate::ai::backend::Turn;
+use crate::config::{data_dir, write_private};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// How many earlier turns are sent back to the model
+const MAX_CONTEXT_TURNS: usize = 10;
+
+/// File holding the name of the most recently used session
+const LAST_SESSION_FILE: &str = ".last";
+
+/// Unnamed sessions kept; older ones are deleted when a new one is saved
+const MAX_UNNAMED_SESSIONS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTurn {
+    pub question: String,
+    pub answer: String,
+    pub model: String,
+    pub asked_at: DateTime<Utc>,
+}
+
+/// A named ASK conversation persisted in the data dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub turns: Vec<SessionTurn>,
+    /// Started by a plain `aido ask`; subject to `MAX_UNNAMED_SESSIONS`
+    #[serde(default)]
+    pub unnamed: bool,
+}
+
+impl Session {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            turns: Vec::new(),
+            unnamed: false,
+        }
+    }
+
+    /// A session the user didn't name, e.g. `ask-20260117-093000-4242`. The
+    /// process id keeps two asks started in the same second apart.
+    pub fn new_unnamed() -> Self {
+        let name = format!(
+            "ask-{}-{}",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        );
+        Self {
+            unnamed: true,
+            ..Self::new(&name)
+        }
+    }
+
+    pub fn record(&mut self, question: &str, answer: &str, model: &str) {
+        self.turns.push(SessionTurn {
+            question: question.to_string(),
+            answer: answer.to_string(),
+            model: model.to_string(),
+            asked_at: Utc::now(),
+        });
+    }
+
+    /// The most recent turns, shaped the way the question prompt is
+    pub fn context_turns(&self) -> Vec<Turn> {
+        let skip = self.turns.len().saturating_sub(MAX_CONTEXT_TURNS);
+        self.turns
+            .iter()
+            .skip(skip)
+            .map(|turn| Turn {
+                user: format!("Question: {}", turn.question),
+                assistant: turn.answer.clone(),
+            })
+            .collect()
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.turns
+            .last()
+            .map(|turn| turn.asked_at)
+            .unwrap_or(self.created_at)
+    }
+}
+
+/// Reads and writes sessions as JSON files under `<data dir>/sessions`
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(data_dir()?.join("sessions")))
+    }
+
+    pub fn load(&self, name: &str) -> Result<Option<Session>> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read session: {}", path.display()))?;
+        let session = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse session: {}", path.display()))?;
+
+        Ok(Some(session))
+    }
+
+    pub fn save(&self, session: &Session) -> Result<()> {
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "Failed to create sessions directory: {}",
+                self.dir.display()
+            )
+        })?;
+
+        // Sessions hold whatever was piped or attached to a question
+        let path = self.path(&session.name)?;
+        write_private(&path, &serde_json::to_string_pretty(session)?)
+            .with_context(|| format!("Failed to write session: {}", path.display()))?;
+        write_private(&self.dir.join(LAST_SESSION_FILE), &session.name)?;
+
+        if session.unnamed {
+            self.prune_unnamed()?;
+        }
+        Ok(())
+    }
+
+    /// Delete all but the `MAX_UNNAMED_SESSIONS` most recent unnamed sessions
+    fn prune_unnamed(&self) -> Result<()> {
+        let stale = self
+            .list()?
+            .into_iter()
+            .filter(|session| session.unnamed)
+            .skip(MAX_UNNAMED_SESSIONS);
+        for session in stale {
+            self.delete(&session.name)?;
+        }
+        Ok(())
+    }
+
+    /// Name of the session used most recently, if it still exists
+    pub fn last(&self) -> Option<String> {
+        let name = fs::read_to_string(self.dir.join(LAST_SESSION_FILE)).ok()?;
+        let name = name.trim().to_string();
+        self.path(&name).ok()?.exists().then_some(name)
+    }
+
+    /// All sessions, most recently updated first
+    pub fn list(&self) -> Result<Vec<Session>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let contents = fs::read_to_string(&path)?;
+                match serde_json::from_str::<Session>(&contents) {
+                    Ok(session) => sessions.push(session),
+                    Err(e) => tracing::warn!("Skipping unreadable session {}: {e}", path.display()),
+                }
+            }
+        }
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at()));
+        Ok(sessions)
+    }
+
+    /// Delete a session; returns false if it didn't exist
+    pub fn delete(&self, name: &str) -> Result<bool> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to delete session: {}", path.display()))?;
+        Ok(true)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    if !valid {
+        anyhow::bail!(
+            "Invalid session name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tempfile::TempDir;
+
+    fn temp_store() -> (TempDir, SessionStore) {
+        let dir = temp_dir();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        (dir, store)
+    }
+
+    #[test]
+    fn test_save_load_and_last() {
+        let (_dir, store) = temp_store();
+        let mut session = Session::new("rust");
+        session.record("how do I list files?", "Use `ls`.", "claude-test");
+        store.save(&session).unwrap();
+
+        let loaded = store.load("rust").unwrap().unwrap();
+        assert_eq!(loaded.turns.len(), 1);
+        assert_eq!(store.last().as_deref(), Some("rust"));
+
+        assert!(store.delete("rust").unwrap());
+        assert!(store.load("rust").unwrap().is_none());
+        assert_eq!(store.last(), None);
+    }
+
+    #[test]
+    fn test_unnamed_sessions_are_pruned() {
+        let (_dir, store) = temp_store();
+        store.save(&Session::new("kept")).unwrap();
+        for i in 0..MAX_UNNAMED_SESSIONS + 2 {
+            let mut session = Session {
+                unnamed: true,
+                ..Session::new(&format!("ask-{i}"))
+            };
+            session.created_at = Utc::now() + chrono::Duration::seconds(i as i64);
+            store.save(&session).unwrap();
+        }
+
+        let names: Vec<String> = store.list().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names.len(), MAX_UNNAMED_SESSIONS + 1);
+        assert!(names.contains(&"kept".to_string()));
+        assert!(!names.contains(&"ask-0".to_string()) && !names.contains(&"ask-1".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sessions_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, store) = temp_store();
+        store.save(&Session::new("secret")).unwrap();
+        let mode = fs::metadata(store.path("secret").unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_context_turns_are_capped() {
+        let mut session = Session::new("long");
+        for i in 0..15 {
+            session.record(&format!("q{i}"), &format!("a{i}"), "m");
+        }
+
+        let turns = session.context_turns();
+        assert_eq!(turns.len(), MAX_CONTEXT_TURNS);
+        assert_eq!(turns[0].user, "Question: q5");
+        assert_eq!(turns.last().unwrap().assistant, "a14");
+    }
+
+    #[test]
+    fn test_rejects_path_like_names() {
+        let (_dir, store) = temp_store();
+        assert!(store.load("../etc/passwd").is_err());
+        assert!(store.load(".last").is_err());
+        assert!(store.load("my-session_1.2").is_ok());
+    }
+}
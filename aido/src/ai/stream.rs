@@ -0,0 +1,187 @@
+use crate::ai::usage::Usage;
+use serde_json::Value;
+
+/// Incremental decoder for `text/event-stream` bodies.
+///
+/// Feed raw chunks as they arrive; each call returns the `data:` payloads of every
+/// event completed so far. Partial lines (including split UTF-8 sequences) are kept
+/// until the rest arrives.
+#[derive(Default)]
+pub struct SseDecoder {
+    buf: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+        }
+
+        events
+    }
+}
+
+/// One line of `claude -p --output-format stream-json` output
+#[derive(Debug, PartialEq)]
+pub enum CliStreamEvent {
+    /// Incremental text from a partial message
+    Delta(String),
+    /// A complete assistant message
+    Message(String),
+    /// Final result line; `is_error` is set when the CLI reports a failure
+    Result {
+        text: String,
+        is_error: bool,
+        usage: Option<Usage>,
+    },
+    /// Anything we don't render (system/init lines, tool use, ...)
+    Other,
+}
+
+pub fn parse_cli_stream_line(line: &str) -> CliStreamEvent {
+    let Ok(value) = serde_json::from_str::<Value>(line) else {
+        return CliStreamEvent::Other;
+    };
+
+    match value["type"].as_str() {
+        Some("stream_event") => {
+            let event = &value["event"];
+            if event["type"] == "content_block_delta" && event["delta"]["type"] == "text_delta" {
+                if let Some(text) = event["delta"]["text"].as_str() {
+                    return CliStreamEvent::Delta(text.to_string());
+                }
+            }
+            CliStreamEvent::Other
+        }
+        Some("assistant") => {
+            let text = text_blocks(&value["message"]["content"]);
+            if text.is_empty() {
+                CliStreamEvent::Other
+            } else {
+                CliStreamEvent::Message(text)
+            }
+        }
+        Some("result") => CliStreamEvent::Result {
+            text: value["result"].as_str().unwrap_or_default().to_string(),
+            is_error: value["is_error"].as_bool().unwrap_or(false),
+            usage: cli_usage(&value),
+        },
+        _ => CliStreamEvent::Other,
+    }
+}
+
+/// Token counts and cost from a result line (also the `--output-format json` body)
+fn cli_usage(value: &Value) -> Option<Usage> {
+    let usage = value.get("usage")?;
+    let tokens = |key: &str| usage[key].as_u64().unwrap_or(0);
+
+    Some(Usage {
+        // Prompt caching splits the input across three counters
+        input_tokens: tokens("input_tokens")
+            + tokens("cache_creation_input_tokens")
+            + tokens("cache_read_input_tokens"),
+        output_tokens: tokens("output_tokens"),
+        cost_usd: value["total_cost_usd"].as_f64(),
+    })
+}
+
+fn text_blocks(content: &Value) -> String {
+    content
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_handles_split_chunks() {
+        let mut decoder = SseDecoder::new();
+
+        assert!(decoder.push(b"event: message\ndata: {\"a\"").is_empty());
+        assert_eq!(
+            decoder.push(b":1}\n\ndata: two\r\n\r\n"),
+            vec!["{\"a\":1}", "two"]
+        );
+        assert!(decoder.push(b"data: [DONE]").is_empty());
+        assert_eq!(decoder.push(b"\n\n"), vec!["[DONE]"]);
+    }
+
+    #[test]
+    fn test_parse_cli_stream_lines() {
+        let delta = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}}"#;
+        assert_eq!(
+            parse_cli_stream_line(delta),
+            CliStreamEvent::Delta("Hel".to_string())
+        );
+
+        let message =
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Hello"}]}}"#;
+        assert_eq!(
+            parse_cli_stream_line(message),
+            CliStreamEvent::Message("Hello".to_string())
+        );
+
+        let result = r#"{"type":"result","subtype":"success","is_error":false,"result":"Hello"}"#;
+        assert_eq!(
+            parse_cli_stream_line(result),
+            CliStreamEvent::Result {
+                text: "Hello".to_string(),
+                is_error: false,
+                usage: None
+            }
+        );
+
+        assert_eq!(
+            parse_cli_stream_line(r#"{"type":"system","subtype":"init"}"#),
+            CliStreamEvent::Other
+        );
+        assert_eq!(parse_cli_stream_line("not json"), CliStreamEvent::Other);
+    }
+
+    #[test]
+    fn test_parse_cli_result_usage() {
+        let result = r#"{"type":"result","is_error":false,"result":"ls","total_cost_usd":0.0123,
+            "usage":{"input_tokens":4,"cache_creation_input_tokens":1000,"cache_read_input_tokens":200,"output_tokens":7}}"#;
+
+        let CliStreamEvent::Result { usage, .. } = parse_cli_stream_line(result) else {
+            panic!("expected a result line");
+        };
+        assert_eq!(
+            usage,
+            Some(Usage {
+                input_tokens: 1204,
+                output_tokens: 7,
+                cost_usd: Some(0.0123)
+            })
+        );
+    }
+}
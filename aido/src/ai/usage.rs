@@ -0,0 +1,207 @@
+use crate::config::{data_dir, JsonLines};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Token counts (and cost, when known) reported for one model call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Exact for the CLI, estimated for the Anthropic API, unknown for self-hosted servers
+    pub cost_usd: Option<f64>,
+}
+
+impl Usage {
+    pub fn new(input_tokens: u64, output_tokens: u64) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            cost_usd: None,
+        }
+    }
+
+    /// Fill in an estimated cost from Anthropic's list prices
+    pub fn priced_for(mut self, model: &str) -> Self {
+        if let Some((input, output)) = price_per_mtok(model) {
+            self.cost_usd = Some(
+                (self.input_tokens as f64 * input + self.output_tokens as f64 * output)
+                    / 1_000_000.0,
+            );
+        }
+        self
+    }
+}
+
+/// USD per million (input, output) tokens, by model family
+fn price_per_mtok(model: &str) -> Option<(f64, f64)> {
+    let model = model.to_lowercase();
+    if model.contains("opus") {
+        Some((15.0, 75.0))
+    } else if model.contains("sonnet") {
+        Some((3.0, 15.0))
+    } else if model.contains("haiku") {
+        Some((0.8, 4.0))
+    } else {
+        None
+    }
+}
+
+/// One line of the usage log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub at: DateTime<Utc>,
+    pub mode: String,
+    pub backend: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageRecord {
+    pub fn new(mode: &str, backend: &str, model: &str, usage: Usage) -> Self {
+        Self {
+            at: Utc::now(),
+            mode: mode.to_string(),
+            backend: backend.to_string(),
+            model: model.to_string(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cost_usd: usage.cost_usd,
+        }
+    }
+
+    /// The local calendar day the call was made on
+    pub fn day(&self) -> NaiveDate {
+        self.at.with_timezone(&Local).date_naive()
+    }
+}
+
+/// Append-only JSON-lines log of every model call at `<data dir>/usage.jsonl`
+pub type UsageLog = JsonLines<UsageRecord>;
+
+impl UsageLog {
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(data_dir()?.join("usage.jsonl")))
+    }
+
+    /// Known spend for a local calendar day
+    pub fn spent_on(&self, day: NaiveDate) -> Result<f64> {
+        Ok(self
+            .records()?
+            .iter()
+            .filter(|record| record.day() == day)
+            .filter_map(|record| record.cost_usd)
+            .sum())
+    }
+}
+
+/// Totals for one (day, mode, model) group of the usage report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageSummary {
+    pub day: NaiveDate,
+    pub mode: String,
+    pub model: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Calls whose cost the backend didn't report
+    pub unpriced_calls: u64,
+}
+
+/// Group records by day, mode and model; most recent day first
+pub fn summarize(records: &[UsageRecord]) -> Vec<UsageSummary> {
+    let mut groups: BTreeMap<(NaiveDate, String, String), UsageSummary> = BTreeMap::new();
+
+    for record in records {
+        let day = record.day();
+        let summary = groups
+            .entry((day, record.mode.clone(), record.model.clone()))
+            .or_insert_with(|| UsageSummary {
+                day,
+                mode: record.mode.clone(),
+                model: record.model.clone(),
+                ..Default::default()
+            });
+
+        summary.calls += 1;
+        summary.input_tokens += record.input_tokens;
+        summary.output_tokens += record.output_tokens;
+        match record.cost_usd {
+            Some(cost) => summary.cost_usd += cost,
+            None => summary.unpriced_calls += 1,
+        }
+    }
+
+    let mut summaries: Vec<UsageSummary> = groups.into_values().collect();
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.day));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn record(mode: &str, model: &str, cost: Option<f64>) -> UsageRecord {
+        UsageRecord::new(
+            mode,
+            "cli",
+            model,
+            Usage {
+                input_tokens: 100,
+                output_tokens: 10,
+                cost_usd: cost,
+            },
+        )
+    }
+
+    #[test]
+    fn test_priced_for_known_families() {
+        let usage = Usage::new(1_000_000, 100_000).priced_for("claude-sonnet-4-20250514");
+        assert!((usage.cost_usd.unwrap() - 4.5).abs() < 1e-9);
+
+        assert_eq!(Usage::new(10, 10).priced_for("llama3").cost_usd, None);
+    }
+
+    #[test]
+    fn test_append_and_spent_today() {
+        let dir = temp_dir();
+        let log = UsageLog::new(dir.path().join("usage.jsonl"));
+
+        log.append(&record("do", "sonnet", Some(0.02))).unwrap();
+        log.append(&record("ask", "sonnet", Some(0.03))).unwrap();
+        log.append(&record("ask", "llama3", None)).unwrap();
+
+        assert_eq!(log.records().unwrap().len(), 3);
+        let today = Local::now().date_naive();
+        assert!((log.spent_on(today).unwrap() - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summarize_groups_by_day_mode_and_model() {
+        let records = vec![
+            record("ask", "sonnet", Some(0.01)),
+            record("ask", "sonnet", Some(0.02)),
+            record("do", "sonnet", Some(0.05)),
+            record("ask", "llama3", None),
+        ];
+
+        let summary = summarize(&records);
+        assert_eq!(summary.len(), 3);
+
+        let ask = summary
+            .iter()
+            .find(|s| s.mode == "ask" && s.model == "sonnet")
+            .unwrap();
+        assert_eq!(ask.calls, 2);
+        assert_eq!(ask.input_tokens, 200);
+        assert!((ask.cost_usd - 0.03).abs() < 1e-9);
+
+        let local = summary.iter().find(|s| s.model == "llama3").unwrap();
+        assert_eq!(local.unpriced_calls, 1);
+    }
+}
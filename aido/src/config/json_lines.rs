@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+/// Append-only log with one JSON-encoded `T` per line
+pub struct JsonLines<T> {
+    path: PathBuf,
+    record: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> JsonLines<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            record: PhantomData,
+        }
+    }
+
+    pub fn append(&self, record: &T) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create data directory: {}", dir.display()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open log: {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+        Ok(())
+    }
+
+    /// Every readable record, oldest first; corrupt lines are skipped
+    pub fn records(&self) -> Result<Vec<T>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read log: {}", self.path.display()))?;
+
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
@@ -1,6 +1,12 @@
+pub mod files;
+pub mod json_lines;
 pub mod settings;
 
+pub use files::write_private;
+pub use json_lines::JsonLines;
+
 pub use settings::{
-    binding_label, binding_to_bash, binding_to_fish, binding_to_zsh, init_config, load_config,
-    save_config, AidoConfig, KNOWN_BINDINGS,
+    binding_label, binding_to_bash, binding_to_fish, binding_to_zsh, compile_patterns, config_dir,
+    data_dir, init_config, load_config, save_config, AidoConfig, BackendKind, ContextConfig,
+    RedactConfig, KNOWN_BINDINGS,
 };
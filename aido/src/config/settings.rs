@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -10,12 +11,77 @@ pub struct AidoConfig {
     pub ui: UiConfig,
     #[serde(default)]
     pub keybindings: KeybindingsConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
+    #[serde(default)]
+    pub redact: RedactConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiConfig {
+    #[serde(default)]
+    pub backend: BackendKind,
     pub claude_command: String,
     pub model: String,
+    #[serde(default = "default_api_base_url")]
+    pub api_base_url: String,
+    #[serde(default = "default_api_key_env")]
+    pub api_key_env: String,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    /// Seconds to wait for a response; 0 disables the limit
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Models tried in order after `model` keeps failing
+    #[serde(default)]
+    pub fallback_models: Vec<String>,
+    /// Extra attempts per model for transient errors
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles on each further attempt
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Refuse further AI calls once this many USD have been spent today
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_budget: Option<f64>,
+    #[serde(default = "default_openai_base_url")]
+    pub openai_base_url: String,
+    #[serde(default)]
+    pub openai_model: String,
+    /// Environment variable holding the OpenAI-compatible server's bearer token
+    #[serde(default = "default_openai_api_key_env")]
+    pub openai_api_key_env: String,
+    /// Token stored in the file; the environment variable takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openai_api_key: Option<String>,
+}
+
+/// Which AI backend handles requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Shell out to the Claude Code CLI (`claude -p`)
+    #[default]
+    Cli,
+    /// Call the Anthropic Messages API directly over HTTP
+    Api,
+    /// Call an OpenAI-compatible `/v1/chat/completions` server
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+impl std::fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendKind::Cli => write!(f, "cli"),
+            BackendKind::Api => write!(f, "api"),
+            BackendKind::OpenAi => write!(f, "openai"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +92,9 @@ pub struct ExecutionConfig {
     pub dry_run: bool,
     #[serde(default)]
     pub unsafe_mode: bool,
+    /// Rounds of read-only probes allowed before the plan (`aido do --investigate`)
+    #[serde(default = "default_max_probe_rounds")]
+    pub max_probe_rounds: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +112,63 @@ pub struct KeybindingsConfig {
     pub do_binding: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    #[serde(default = "default_cache_enabled")]
+    pub enabled: bool,
+    /// How long a cached DO response stays valid
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+/// Which context providers feed the `{context}` block of prompts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextConfig {
+    /// Working directory
+    #[serde(default = "default_provider_enabled")]
+    pub cwd: bool,
+    /// Shell, OS, date and time
+    #[serde(default = "default_provider_enabled")]
+    pub env: bool,
+    /// Distro, installed tools and GNU/BSD flavours
+    #[serde(default = "default_provider_enabled")]
+    pub tools: bool,
+    /// Current git branch
+    #[serde(default = "default_provider_enabled")]
+    pub git: bool,
+    /// Project type and its tasks
+    #[serde(default = "default_provider_enabled")]
+    pub project: bool,
+    /// Include recent shell history in DO prompts (off by default: it may hold secrets)
+    #[serde(default)]
+    pub history: bool,
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// Regexes for history entries that are never sent
+    #[serde(default = "default_history_ignore")]
+    pub history_ignore: Vec<String>,
+    /// Piped input beyond this is cut from the middle, keeping its head and tail
+    #[serde(default = "default_stdin_max_bytes")]
+    pub stdin_max_bytes: usize,
+    /// Per-file limit for `--file` attachments, truncated the same way
+    #[serde(default = "default_file_max_bytes")]
+    pub file_max_bytes: usize,
+}
+
+/// Secret redaction applied to everything sent to the model or saved to disk,
+/// except cached DO responses, which keep the commands as they will run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactConfig {
+    #[serde(default = "default_redact_enabled")]
+    pub enabled: bool,
+    /// Also redact long random-looking tokens that no specific detector matched
+    #[serde(default = "default_redact_high_entropy")]
+    pub high_entropy: bool,
+    /// Extra regexes to redact; with a capture group only the group is replaced
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
 pub const KNOWN_BINDINGS: &[(&str, &str)] = &[
     ("ctrl-o", "Ctrl+O"),
     ("ctrl-k", "Ctrl+K"),
@@ -50,6 +176,21 @@ pub const KNOWN_BINDINGS: &[(&str, &str)] = &[
     ("ctrl-alt-k", "Ctrl+Alt+K"),
 ];
 
+/// Compile the regexes listed in `setting`. A typo in one pattern shouldn't stop
+/// aido from working, so bad ones are logged and left out.
+pub fn compile_patterns(setting: &str, patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid {setting} pattern {pattern:?}: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
 pub fn binding_label(binding: &str) -> String {
     KNOWN_BINDINGS
         .iter()
@@ -88,6 +229,86 @@ pub fn binding_to_fish(binding: &str) -> String {
     }
 }
 
+fn default_api_base_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_api_key_env() -> String {
+    "ANTHROPIC_API_KEY".to_string()
+}
+
+fn default_openai_api_key_env() -> String {
+    "OPENAI_API_KEY".to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    4096
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_openai_base_url() -> String {
+    "http://localhost:8080".to_string()
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_provider_enabled() -> bool {
+    true
+}
+
+fn default_max_probe_rounds() -> u32 {
+    3
+}
+
+fn default_history_limit() -> usize {
+    10
+}
+
+fn default_history_ignore() -> Vec<String> {
+    vec![
+        r"^aido\b".to_string(),
+        r"^(ls|cd|pwd|clear|exit|history)$".to_string(),
+        r"(?i)(password|passwd|secret|token|api[_-]?key)".to_string(),
+    ]
+}
+
+fn default_redact_enabled() -> bool {
+    true
+}
+
+fn default_redact_high_entropy() -> bool {
+    true
+}
+
+fn default_stdin_max_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_file_max_bytes() -> usize {
+    32 * 1024
+}
+
 fn default_ask_binding() -> String {
     "ctrl-o".to_string()
 }
@@ -105,12 +326,81 @@ impl Default for KeybindingsConfig {
     }
 }
 
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_cache_enabled(),
+            ttl_secs: default_cache_ttl_secs(),
+        }
+    }
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            cwd: default_provider_enabled(),
+            env: default_provider_enabled(),
+            tools: default_provider_enabled(),
+            git: default_provider_enabled(),
+            project: default_provider_enabled(),
+            history: false,
+            history_limit: default_history_limit(),
+            history_ignore: default_history_ignore(),
+            stdin_max_bytes: default_stdin_max_bytes(),
+            file_max_bytes: default_file_max_bytes(),
+        }
+    }
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_redact_enabled(),
+            high_entropy: default_redact_high_entropy(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl AidoConfig {
+    /// A copy that is safe to print: stored secrets are masked
+    pub fn for_display(&self) -> Self {
+        let mut config = self.clone();
+        config.ai.openai_api_key = config.ai.openai_api_key.as_deref().map(mask_secret);
+        config
+    }
+}
+
+/// Everything but the last four characters hidden, and all of a short secret
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "********".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("********{}", tail)
+}
+
 impl Default for AidoConfig {
     fn default() -> Self {
         Self {
             ai: AiConfig {
+                backend: BackendKind::default(),
                 claude_command: String::from("claude"),
                 model: String::from("claude-sonnet-4-20250514"),
+                api_base_url: default_api_base_url(),
+                api_key_env: default_api_key_env(),
+                max_tokens: default_max_tokens(),
+                stream: default_stream(),
+                timeout_secs: default_timeout_secs(),
+                fallback_models: Vec::new(),
+                max_retries: default_max_retries(),
+                retry_backoff_ms: default_retry_backoff_ms(),
+                daily_budget: None,
+                openai_base_url: default_openai_base_url(),
+                openai_model: String::new(),
+                openai_api_key_env: default_openai_api_key_env(),
+                openai_api_key: None,
             },
             execution: ExecutionConfig {
                 always_confirm: true,
@@ -123,6 +413,7 @@ impl Default for AidoConfig {
                 ],
                 dry_run: false,
                 unsafe_mode: false,
+                max_probe_rounds: default_max_probe_rounds(),
             },
             ui: UiConfig {
                 style: "terminal".to_string(),
@@ -130,16 +421,33 @@ impl Default for AidoConfig {
                 show_context: true,
             },
             keybindings: KeybindingsConfig::default(),
+            cache: CacheConfig::default(),
+            context: ContextConfig::default(),
+            redact: RedactConfig::default(),
         }
     }
 }
 
-fn config_path() -> Result<PathBuf> {
+/// Directory holding `config.toml` and user overrides such as prompt templates
+pub fn config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
         .join("aido");
 
-    Ok(config_dir.join("config.toml"))
+    Ok(config_dir)
+}
+
+fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+/// Directory for AIDO's persistent data (cache, history, ...)
+pub fn data_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?
+        .join("aido");
+
+    Ok(data_dir)
 }
 
 pub fn load_config() -> Result<AidoConfig> {
@@ -212,3 +520,23 @@ pub fn init_config() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_masks_the_openai_key() {
+        let mut config = AidoConfig::default();
+        config.ai.openai_api_key = Some("sk-local-0123456789abcd".to_string());
+        let shown = serde_json::to_string(&config.for_display()).unwrap();
+        assert!(!shown.contains("sk-local-0123456789"), "{shown}");
+        assert!(shown.contains("********abcd"), "{shown}");
+
+        config.ai.openai_api_key = Some("short".to_string());
+        assert_eq!(
+            config.for_display().ai.openai_api_key.as_deref(),
+            Some("********")
+        );
+    }
+}
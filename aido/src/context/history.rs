@@ -0,0 +1,300 @@
+use crate::config::{compile_patterns, ContextConfig};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Only the end of the history file is read; it can grow to many megabytes
+const TAIL_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    /// Plain or `EXTENDED_HISTORY` (`: <time>:<duration>;<command>`)
+    Zsh,
+    /// One command per line, with optional `#<time>` comment lines
+    Bash,
+    /// YAML-ish `- cmd: <command>` entries
+    Fish,
+}
+
+impl HistoryFormat {
+    /// Format of the user's login shell, from `$SHELL`
+    pub fn detect() -> Option<Self> {
+        let shell = env::var("SHELL").ok()?;
+        match Path::new(&shell).file_name()?.to_str()? {
+            "zsh" => Some(Self::Zsh),
+            "bash" => Some(Self::Bash),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+
+    /// `$HISTFILE` when exported, otherwise the shell's default location
+    pub fn history_path(self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+
+        match self {
+            Self::Zsh | Self::Bash => {
+                if let Some(path) = env::var_os("HISTFILE").filter(|p| !p.is_empty()) {
+                    return Some(PathBuf::from(path));
+                }
+                if self == Self::Zsh {
+                    let dir = env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or(home);
+                    Some(dir.join(".zsh_history"))
+                } else {
+                    Some(home.join(".bash_history"))
+                }
+            }
+            Self::Fish => {
+                let data = env::var_os("XDG_DATA_HOME")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home.join(".local/share"));
+                Some(data.join("fish/fish_history"))
+            }
+        }
+    }
+
+    /// Commands in the order they were run
+    pub fn parse(self, text: &str) -> Vec<String> {
+        match self {
+            Self::Zsh => parse_zsh(text),
+            Self::Bash => parse_bash(text),
+            Self::Fish => parse_fish(text),
+        }
+    }
+}
+
+/// The most recent commands from the user's shell history, for prompts like
+/// "do that again but for staging"
+pub struct ShellHistory {
+    pub path: PathBuf,
+    pub commands: Vec<String>,
+}
+
+impl ShellHistory {
+    /// `None` when history context is turned off or the shell isn't recognised
+    pub fn load(config: &ContextConfig) -> Result<Option<Self>> {
+        if !config.history {
+            return Ok(None);
+        }
+
+        let Some(format) = HistoryFormat::detect() else {
+            return Ok(None);
+        };
+        let Some(path) = format.history_path() else {
+            return Ok(None);
+        };
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let ignore = compile_patterns("[context] history_ignore", &config.history_ignore);
+        let commands = format.parse(&read_tail(&path)?);
+
+        Ok(Some(Self {
+            commands: recent(&commands, config.history_limit, &ignore),
+            path,
+        }))
+    }
+
+    pub fn to_context(&self) -> String {
+        if self.commands.is_empty() {
+            return String::new();
+        }
+
+        let mut context = String::from("Recent shell commands (oldest first):\n");
+        for command in &self.commands {
+            context.push_str(&format!("- {}\n", command));
+        }
+        context
+    }
+}
+
+fn read_tail(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open shell history: {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    // History files aren't guaranteed to be UTF-8 (zsh metafies some bytes)
+    let text = String::from_utf8_lossy(&bytes);
+
+    // Starting mid-file almost always lands inside an entry; drop the partial line
+    Ok(if start > 0 {
+        text.split_once('\n')
+            .map(|(_, rest)| rest.to_string())
+            .unwrap_or_default()
+    } else {
+        text.into_owned()
+    })
+}
+
+/// The last `limit` distinct commands, oldest first. A repeated command keeps its
+/// latest position.
+fn recent(commands: &[String], limit: usize, ignore: &[Regex]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut recent: Vec<String> = commands
+        .iter()
+        .rev()
+        .map(|command| command.trim())
+        .filter(|command| !command.is_empty())
+        .filter(|command| !ignore.iter().any(|regex| regex.is_match(command)))
+        .filter(|command| seen.insert(*command))
+        .take(limit)
+        .map(str::to_string)
+        .collect();
+
+    recent.reverse();
+    recent
+}
+
+fn parse_zsh(text: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in text.lines() {
+        let line = match &mut current {
+            Some(pending) => {
+                pending.push('\n');
+                pending.push_str(line);
+                pending.clone()
+            }
+            None => strip_zsh_timestamp(line).to_string(),
+        };
+
+        // A trailing backslash continues the command on the next line
+        if let Some(continued) = line.strip_suffix('\\') {
+            current = Some(continued.to_string());
+        } else {
+            current = None;
+            commands.push(line);
+        }
+    }
+
+    commands.extend(current);
+    commands
+}
+
+fn strip_zsh_timestamp(line: &str) -> &str {
+    line.strip_prefix(": ")
+        .and_then(|rest| rest.split_once(';'))
+        .filter(|(meta, _)| {
+            meta.split(':')
+                .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|(_, command)| command)
+        .unwrap_or(line)
+}
+
+fn parse_bash(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| {
+            !line
+                .strip_prefix('#')
+                .is_some_and(|ts| !ts.is_empty() && ts.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_fish(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(unescape_fish)
+        .collect()
+}
+
+/// fish stores newlines as `\n` and backslashes as `\\`
+fn unescape_fish(command: &str) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_history_formats() {
+        let zsh = ": 1700000000:0;git status\nls -la\n: 1700000050:3;docker build \\\n  -t app .\n";
+        assert_eq!(
+            HistoryFormat::Zsh.parse(zsh),
+            strings(&["git status", "ls -la", "docker build \n  -t app ."])
+        );
+
+        let bash = "#1700000000\naws s3 ls s3://prod-bucket\n# a comment\nmake\n";
+        assert_eq!(
+            HistoryFormat::Bash.parse(bash),
+            strings(&["aws s3 ls s3://prod-bucket", "# a comment", "make"])
+        );
+
+        let fish = "- cmd: echo a\\\\b\n  when: 1700000000\n- cmd: printf 'x'\\nls\n  when: 1700000001\n  paths:\n    - x\n";
+        assert_eq!(
+            HistoryFormat::Fish.parse(fish),
+            strings(&["echo a\\b", "printf 'x'\nls"])
+        );
+    }
+
+    #[test]
+    fn test_recent_dedupes_and_ignores() {
+        let commands = strings(&[
+            "git pull",
+            "cargo test",
+            "aido do \"list files\"",
+            "git pull",
+            "",
+            "kubectl get pods",
+        ]);
+        let ignore = compile_patterns("history_ignore", &strings(&[r"^aido\b", "[invalid"]));
+
+        assert_eq!(
+            recent(&commands, 10, &ignore),
+            strings(&["cargo test", "git pull", "kubectl get pods"])
+        );
+        assert_eq!(
+            recent(&commands, 2, &ignore),
+            strings(&["git pull", "kubectl get pods"])
+        );
+    }
+
+    #[test]
+    fn test_read_tail_drops_partial_first_line() {
+        let dir = temp_dir();
+        let path = dir.path().join("history");
+        let line = "echo ".to_string() + &"x".repeat(100) + "\n";
+        std::fs::write(&path, line.repeat(TAIL_BYTES as usize / line.len() + 10)).unwrap();
+
+        let tail = read_tail(&path).unwrap();
+        assert!(tail.len() <= TAIL_BYTES as usize);
+        assert!(tail.lines().all(|l| l == line.trim_end()));
+    }
+}
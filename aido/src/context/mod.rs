@@ -0,0 +1,184 @@
+pub mod attachment;
+pub mod history;
+pub mod project;
+pub mod providers;
+pub mod tools;
+
+pub use attachment::{with_attachments, Attachment};
+pub use history::ShellHistory;
+pub use project::ProjectInfo;
+pub use providers::ContextProvider;
+pub use tools::{date_context, without_time_of_day, ToolInventory};
+
+use crate::ai::prompts::PromptKind;
+use crate::ai::redact::Redactor;
+use crate::config::ContextConfig;
+use providers::{
+    CwdProvider, EnvProvider, GitProvider, HistoryProvider, ProjectProvider, ToolsProvider,
+};
+
+/// What one provider contributed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextSection {
+    pub provider: &'static str,
+    pub text: String,
+}
+
+/// The `{context}` block of a prompt, gathered from the enabled providers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptContext {
+    pub sections: Vec<ContextSection>,
+}
+
+impl PromptContext {
+    /// Context for a prompt of the given kind. Nothing is gathered when the
+    /// prompt's template has no `{context}` placeholder.
+    pub fn for_prompt(config: &ContextConfig, kind: PromptKind) -> Self {
+        if !kind.template().contains("{context}") {
+            return Self::default();
+        }
+        Self::gather(&providers(config, kind))
+    }
+
+    /// Run each provider in order. A failing provider is skipped with a warning.
+    pub fn gather(providers: &[Box<dyn ContextProvider>]) -> Self {
+        let sections = providers
+            .iter()
+            .filter_map(|provider| match provider.collect() {
+                Ok(Some(text)) if !text.is_empty() => Some(ContextSection {
+                    provider: provider.name(),
+                    text,
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    tracing::warn!("Skipping {} context: {e}", provider.name());
+                    None
+                }
+            })
+            .collect();
+
+        Self { sections }
+    }
+
+    /// The same context with secrets replaced, as it will be sent and shown
+    pub fn redacted(self, redactor: &Redactor) -> Self {
+        Self {
+            sections: self
+                .sections
+                .into_iter()
+                .map(|section| ContextSection {
+                    provider: section.provider,
+                    text: redactor.redact(&section.text),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The text substituted for `{context}`
+    pub fn render(&self) -> String {
+        self.sections
+            .iter()
+            .map(|section| section.text.as_str())
+            .collect()
+    }
+}
+
+/// The enabled providers for a prompt, in the order their output appears.
+/// Shell history only goes to DO prompts.
+pub fn providers(config: &ContextConfig, kind: PromptKind) -> Vec<Box<dyn ContextProvider>> {
+    let mut providers: Vec<Box<dyn ContextProvider>> = Vec::new();
+
+    if config.cwd {
+        providers.push(Box::new(CwdProvider));
+    }
+    if config.env {
+        providers.push(Box::new(EnvProvider));
+    }
+    if config.tools {
+        providers.push(Box::new(ToolsProvider));
+    }
+    if config.git {
+        providers.push(Box::new(GitProvider));
+    }
+    if config.project {
+        providers.push(Box::new(ProjectProvider));
+    }
+    if config.history && kind == PromptKind::Do {
+        providers.push(Box::new(HistoryProvider {
+            config: config.clone(),
+        }));
+    }
+
+    providers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    struct Fixed(&'static str, Result<Option<String>, &'static str>);
+
+    impl ContextProvider for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn collect(&self) -> Result<Option<String>> {
+            self.1.clone().map_err(|e| anyhow::anyhow!(e))
+        }
+    }
+
+    #[test]
+    fn test_gather_skips_empty_and_failing_providers() {
+        let providers: Vec<Box<dyn ContextProvider>> = vec![
+            Box::new(Fixed(
+                "cwd",
+                Ok(Some("Working directory: /tmp\n".to_string())),
+            )),
+            Box::new(Fixed("git", Ok(None))),
+            Box::new(Fixed("tools", Err("probe failed"))),
+            Box::new(Fixed("env", Ok(Some("OS: linux\n".to_string())))),
+        ];
+
+        let context = PromptContext::gather(&providers);
+        assert_eq!(
+            context
+                .sections
+                .iter()
+                .map(|section| section.provider)
+                .collect::<Vec<_>>(),
+            ["cwd", "env"]
+        );
+        assert_eq!(context.render(), "Working directory: /tmp\nOS: linux\n");
+    }
+
+    #[test]
+    fn test_providers_follow_config_toggles() {
+        let names = |config: &ContextConfig, kind| {
+            providers(config, kind)
+                .iter()
+                .map(|provider| provider.name())
+                .collect::<Vec<_>>()
+        };
+
+        let mut config = ContextConfig::default();
+        assert_eq!(
+            names(&config, PromptKind::Do),
+            ["cwd", "env", "tools", "git", "project"]
+        );
+
+        config.tools = false;
+        config.git = false;
+        config.history = true;
+        assert_eq!(
+            names(&config, PromptKind::Do),
+            ["cwd", "env", "project", "history"]
+        );
+        assert_eq!(names(&config, PromptKind::Ask), ["cwd", "env", "project"]);
+    }
+}
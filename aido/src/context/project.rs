@@ -0,0 +1,382 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Keeps huge Makefiles and monorepo package.json files from flooding the prompt
+const MAX_TASKS_PER_RUNNER: usize = 30;
+
+lazy_static! {
+    static ref MAKE_TARGET_REGEX: Regex =
+        Regex::new(r"^([A-Za-z0-9][A-Za-z0-9_.\-/]*)\s*:").unwrap();
+    static ref JUST_RECIPE_REGEX: Regex =
+        Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_\-]*)[^:]*:").unwrap();
+}
+
+/// Tasks a project defines for one runner, e.g. `npm run` scripts or `make` targets
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSet {
+    /// How to invoke them, e.g. `pnpm run`, `make`, `docker compose` services
+    pub runner: String,
+    pub names: Vec<String>,
+}
+
+/// What kind of project the working directory belongs to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectInfo {
+    pub root: PathBuf,
+    /// Detected ecosystems, e.g. "Rust (Cargo)", "Node.js (pnpm)"
+    pub kinds: Vec<String>,
+    pub tasks: Vec<TaskSet>,
+}
+
+/// Files that mark a project root
+const MARKERS: [&str; 19] = [
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "requirements.txt",
+    "go.mod",
+    "Gemfile",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Makefile",
+    "makefile",
+    "GNUmakefile",
+    "justfile",
+    "Justfile",
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    "compose.yaml",
+    "compose.yml",
+];
+
+impl ProjectInfo {
+    /// Detect the project containing `start`: the nearest directory with a known
+    /// manifest, looking no further up than the git repository root.
+    pub fn detect(start: &Path) -> Option<Self> {
+        let root = project_root(start)?;
+        let info = Self::detect_in(&root);
+        (!info.kinds.is_empty() || !info.tasks.is_empty()).then_some(info)
+    }
+
+    pub fn detect_in(dir: &Path) -> Self {
+        let mut info = ProjectInfo {
+            root: dir.to_path_buf(),
+            ..Default::default()
+        };
+
+        if let Some(cargo) = read(dir, "Cargo.toml") {
+            let workspace = cargo
+                .parse::<toml::Table>()
+                .is_ok_and(|table| table.contains_key("workspace"));
+            info.kinds.push(if workspace {
+                "Rust (Cargo workspace)".to_string()
+            } else {
+                "Rust (Cargo)".to_string()
+            });
+        }
+
+        if let Some(package) = read(dir, "package.json") {
+            let manager = node_package_manager(dir);
+            info.kinds.push(format!("Node.js ({manager})"));
+            let scripts = package_scripts(&package);
+            info.push_tasks(format!("{manager} run"), scripts);
+        }
+
+        if let Some(pyproject) = read(dir, "pyproject.toml") {
+            let (tool, scripts) = python_project(dir, &pyproject);
+            info.kinds.push(format!("Python ({tool})"));
+            info.push_tasks("Python entry points".to_string(), scripts);
+        } else if dir.join("setup.py").exists() || dir.join("requirements.txt").exists() {
+            info.kinds.push("Python (pip)".to_string());
+        }
+
+        for (marker, kind) in [
+            ("go.mod", "Go"),
+            ("Gemfile", "Ruby (Bundler)"),
+            ("pom.xml", "Java (Maven)"),
+            ("build.gradle", "JVM (Gradle)"),
+            ("build.gradle.kts", "JVM (Gradle)"),
+        ] {
+            if dir.join(marker).exists() && !info.kinds.iter().any(|k| k == kind) {
+                info.kinds.push(kind.to_string());
+            }
+        }
+
+        if let Some(makefile) = ["GNUmakefile", "makefile", "Makefile"]
+            .iter()
+            .find_map(|name| read(dir, name))
+        {
+            info.push_tasks("make".to_string(), make_targets(&makefile));
+        }
+
+        if let Some(justfile) = ["justfile", "Justfile", ".justfile"]
+            .iter()
+            .find_map(|name| read(dir, name))
+        {
+            info.push_tasks("just".to_string(), just_recipes(&justfile));
+        }
+
+        if let Some(compose) = [
+            "compose.yaml",
+            "compose.yml",
+            "docker-compose.yml",
+            "docker-compose.yaml",
+        ]
+        .iter()
+        .find_map(|name| read(dir, name))
+        {
+            info.push_tasks(
+                "docker compose services".to_string(),
+                compose_services(&compose),
+            );
+        }
+
+        info
+    }
+
+    fn push_tasks(&mut self, runner: String, mut names: Vec<String>) {
+        if names.is_empty() {
+            return;
+        }
+        names.truncate(MAX_TASKS_PER_RUNNER);
+        self.tasks.push(TaskSet { runner, names });
+    }
+
+    /// Context lines telling the model which project tooling to prefer
+    pub fn to_context(&self) -> String {
+        let mut context = String::new();
+
+        if !self.kinds.is_empty() {
+            context.push_str(&format!("Project type: {}\n", self.kinds.join(", ")));
+        }
+        if !self.tasks.is_empty() {
+            context.push_str("Project tasks (prefer these over inventing equivalent commands):\n");
+            for tasks in &self.tasks {
+                context.push_str(&format!("- {}: {}\n", tasks.runner, tasks.names.join(", ")));
+            }
+        }
+
+        context
+    }
+}
+
+fn project_root(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        if MARKERS.iter().any(|marker| dir.join(marker).exists()) {
+            return Some(dir.to_path_buf());
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+fn read(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok()
+}
+
+fn node_package_manager(dir: &Path) -> &'static str {
+    if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else if dir.join("bun.lockb").exists() || dir.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+fn package_scripts(package: &str) -> Vec<String> {
+    serde_json::from_str::<Value>(package)
+        .ok()
+        .and_then(|value| {
+            value["scripts"]
+                .as_object()
+                .map(|scripts| scripts.keys().cloned().collect())
+        })
+        .unwrap_or_default()
+}
+
+/// The packaging tool in use and the names of `[project.scripts]`/`[tool.poetry.scripts]`
+fn python_project(dir: &Path, pyproject: &str) -> (&'static str, Vec<String>) {
+    let table = pyproject.parse::<toml::Table>().unwrap_or_default();
+    let tool = table.get("tool").and_then(|tool| tool.as_table());
+    let has_tool = |name: &str| tool.is_some_and(|tool| tool.contains_key(name));
+
+    let manager = if dir.join("uv.lock").exists() || has_tool("uv") {
+        "uv"
+    } else if has_tool("poetry") {
+        "poetry"
+    } else if has_tool("pdm") {
+        "pdm"
+    } else if has_tool("hatch") {
+        "hatch"
+    } else {
+        "pip"
+    };
+
+    let script_tables = [
+        table.get("project").and_then(|p| p.get("scripts")),
+        tool.and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("scripts")),
+    ];
+    let scripts = script_tables
+        .into_iter()
+        .flatten()
+        .filter_map(|scripts| scripts.as_table())
+        .flat_map(|scripts| scripts.keys().cloned())
+        .collect();
+
+    (manager, scripts)
+}
+
+fn make_targets(makefile: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+
+    for line in makefile.lines() {
+        // Variable assignments (`X := y`, `X ::= y`) look like rules to the regex
+        let Some(captures) = MAKE_TARGET_REGEX.captures(line) else {
+            continue;
+        };
+        if line[captures.get(0).unwrap().end()..].starts_with('=') {
+            continue;
+        }
+
+        let target = &captures[1];
+        if !target.contains('%') && !targets.iter().any(|t| t == target) {
+            targets.push(target.to_string());
+        }
+    }
+
+    targets
+}
+
+fn just_recipes(justfile: &str) -> Vec<String> {
+    justfile
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#']))
+        .filter_map(|line| {
+            let captures = JUST_RECIPE_REGEX.captures(line)?;
+            let rest = &line[captures.get(0).unwrap().end()..];
+            let name = &captures[1];
+
+            let is_setting = ["set", "alias", "export", "import", "mod"].contains(&name);
+            (!rest.starts_with('=') && !is_setting).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Service names under the top-level `services:` key. Compose files are YAML, but
+/// the services are always the first level of keys below it, so no parser is needed.
+fn compose_services(compose: &str) -> Vec<String> {
+    let mut services = Vec::new();
+    let mut in_services = false;
+    let mut indent = None;
+
+    for line in compose.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let depth = line.len() - trimmed.len();
+        if depth == 0 {
+            in_services = trimmed.trim_end() == "services:";
+            continue;
+        }
+        if !in_services {
+            continue;
+        }
+
+        let level = *indent.get_or_insert(depth);
+        if depth == level {
+            if let Some(name) = trimmed.strip_suffix(':').or_else(|| {
+                trimmed
+                    .split_once(':')
+                    .filter(|(_, rest)| rest.trim().is_empty())
+                    .map(|(name, _)| name)
+            }) {
+                services.push(name.trim_matches(['"', '\'']).to_string());
+            }
+        }
+    }
+
+    services
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tempfile::TempDir;
+
+    fn temp_project(files: &[(&str, &str)]) -> TempDir {
+        let dir = temp_dir();
+        for (file, contents) in files {
+            fs::write(dir.path().join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_detects_node_scripts_and_package_manager() {
+        let dir = temp_project(&[
+            (
+                "package.json",
+                r#"{"name":"web","scripts":{"dev":"vite","test":"vitest"}}"#,
+            ),
+            ("pnpm-lock.yaml", ""),
+        ]);
+
+        let info = ProjectInfo::detect_in(dir.path());
+        assert_eq!(info.kinds, vec!["Node.js (pnpm)"]);
+        assert_eq!(
+            info.tasks,
+            vec![TaskSet {
+                runner: "pnpm run".to_string(),
+                names: vec!["dev".to_string(), "test".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_make_targets_skip_variables_and_patterns() {
+        let makefile = "CC := gcc\nVERSION ?= 1\n.PHONY: build test\nbuild: deps\n\tcc main.c\n%.o: %.c\n\tcc -c $<\ntest:\n\t./run\nbuild:\n";
+        assert_eq!(make_targets(makefile), vec!["build", "test"]);
+    }
+
+    #[test]
+    fn test_just_recipes() {
+        let justfile = "set shell := [\"bash\", \"-c\"]\nversion := \"1\"\n\n# Run tests\ntest *ARGS:\n    cargo test {{ARGS}}\n\n@deploy env='staging': build\n    ./ops/deploy {{env}}\nalias t := test\n";
+        assert_eq!(just_recipes(justfile), vec!["test", "deploy"]);
+    }
+
+    #[test]
+    fn test_compose_services() {
+        let compose = "version: '3'\nservices:\n  web:\n    image: nginx\n    ports:\n      - \"80:80\"\n  db:\n    image: postgres\nvolumes:\n  data:\n";
+        assert_eq!(compose_services(compose), vec!["web", "db"]);
+    }
+
+    #[test]
+    fn test_context_lists_project_and_tasks() {
+        let dir = temp_project(&[
+            ("Cargo.toml", "[package]\nname = \"demo\"\n"),
+            ("justfile", "test:\n    cargo test\n"),
+        ]);
+
+        let context = ProjectInfo::detect(dir.path()).unwrap().to_context();
+        assert_eq!(
+            context,
+            "Project type: Rust (Cargo)\n\
+             Project tasks (prefer these over inventing equivalent commands):\n\
+             - just: test\n"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::config::ContextConfig;
+use crate::context::{date_context, ProjectInfo, ShellHistory, ToolInventory};
+use anyhow::{Context, Result};
+use std::env;
+use std::process::Command;
+
+/// One source of prompt context, switched on and off by `[context] <name>`
+pub trait ContextProvider {
+    fn name(&self) -> &'static str;
+
+    /// Lines to add to the prompt, or `None` when there's nothing to say
+    fn collect(&self) -> Result<Option<String>>;
+}
+
+/// The current working directory
+pub struct CwdProvider;
+
+impl ContextProvider for CwdProvider {
+    fn name(&self) -> &'static str {
+        "cwd"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        let cwd = env::current_dir().context("Failed to read the working directory")?;
+        Ok(Some(format!("Working directory: {}\n", cwd.display())))
+    }
+}
+
+/// Shell, OS and the current date and time
+pub struct EnvProvider;
+
+impl ContextProvider for EnvProvider {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        let mut context = String::new();
+        if let Ok(shell) = env::var("SHELL") {
+            context.push_str(&format!("Shell: {}\n", shell));
+        }
+        context.push_str(&format!("OS: {}\n", env::consts::OS));
+        context.push_str(&date_context());
+        Ok(Some(context))
+    }
+}
+
+/// Distro, installed tools and GNU/BSD flavours (cached)
+pub struct ToolsProvider;
+
+impl ContextProvider for ToolsProvider {
+    fn name(&self) -> &'static str {
+        "tools"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        Ok(Some(ToolInventory::load_or_probe().to_context()))
+    }
+}
+
+/// The current git branch, when inside a repository
+pub struct GitProvider;
+
+impl ContextProvider for GitProvider {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        let Ok(output) = Command::new("git")
+            .args(["branch", "--show-current"])
+            .output()
+        else {
+            return Ok(None);
+        };
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok((!branch.is_empty()).then(|| format!("Git branch: {}\n", branch)))
+    }
+}
+
+/// Project type and the tasks it defines
+pub struct ProjectProvider;
+
+impl ContextProvider for ProjectProvider {
+    fn name(&self) -> &'static str {
+        "project"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        let cwd = env::current_dir().context("Failed to read the working directory")?;
+        Ok(ProjectInfo::detect(&cwd).map(|project| project.to_context()))
+    }
+}
+
+/// Recent shell commands (opt-in)
+pub struct HistoryProvider {
+    pub config: ContextConfig,
+}
+
+impl ContextProvider for HistoryProvider {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn collect(&self) -> Result<Option<String>> {
+        Ok(ShellHistory::load(&self.config)?.map(|history| history.to_context()))
+    }
+}
@@ -0,0 +1,407 @@
+use crate::config::data_dir;
+use anyhow::Result;
+use chrono::Local;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Re-probe at most once a day (or whenever `$PATH` changes)
+const INVENTORY_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Tools worth telling the model about when present
+const KEY_TOOLS: [&str; 28] = [
+    "git",
+    "gh",
+    "docker",
+    "podman",
+    "kubectl",
+    "helm",
+    "rg",
+    "fd",
+    "fzf",
+    "jq",
+    "yq",
+    "bat",
+    "curl",
+    "wget",
+    "rsync",
+    "python3",
+    "node",
+    "cargo",
+    "go",
+    "make",
+    "just",
+    "brew",
+    "apt",
+    "dnf",
+    "pacman",
+    "apk",
+    "systemctl",
+    "launchctl",
+];
+
+/// Tools whose flags differ between GNU, BSD and BusyBox builds
+const FLAVOURED_TOOLS: [&str; 5] = ["sed", "date", "find", "grep", "tar"];
+
+const DATE_LINE_PREFIX: &str = "Current date and time:";
+
+lazy_static! {
+    static ref VERSION_REGEX: Regex = Regex::new(r"\d+(?:\.\d+)+").unwrap();
+    static ref TIME_OF_DAY_REGEX: Regex = Regex::new(r"^\d{2}:\d{2}$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolFlavour {
+    pub name: String,
+    /// "GNU", "BSD", "BusyBox", ...
+    pub flavour: String,
+    pub version: Option<String>,
+}
+
+/// What the host has installed, probed once and cached in the data dir
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolInventory {
+    created_at: u64,
+    /// `$PATH` the probe ran with; a different PATH means different tools
+    path: String,
+    pub distro: Option<String>,
+    pub installed: Vec<String>,
+    pub flavours: Vec<ToolFlavour>,
+}
+
+impl ToolInventory {
+    /// The cached inventory if it is fresh, otherwise a new probe (which is then cached)
+    pub fn load_or_probe() -> Self {
+        let cache_path = match Self::cache_path() {
+            Ok(path) => path,
+            Err(_) => return Self::probe(),
+        };
+
+        let path_var = env::var("PATH").unwrap_or_default();
+        if let Some(cached) = Self::load(&cache_path) {
+            if cached.path == path_var
+                && now().saturating_sub(cached.created_at) < INVENTORY_TTL_SECS
+            {
+                return cached;
+            }
+        }
+
+        let inventory = Self::probe();
+        if let Err(e) = inventory.save(&cache_path) {
+            tracing::warn!("Failed to cache tool inventory: {e}");
+        }
+        inventory
+    }
+
+    pub fn probe() -> Self {
+        let path_var = env::var("PATH").unwrap_or_default();
+
+        Self {
+            created_at: now(),
+            distro: distro(),
+            installed: KEY_TOOLS
+                .iter()
+                .filter(|tool| find_in_path(tool, &path_var).is_some())
+                .map(|tool| tool.to_string())
+                .collect(),
+            flavours: FLAVOURED_TOOLS
+                .iter()
+                .filter(|tool| find_in_path(tool, &path_var).is_some())
+                .map(|tool| probe_flavour(tool))
+                .collect(),
+            path: path_var,
+        }
+    }
+
+    fn cache_path() -> Result<PathBuf> {
+        Ok(data_dir()?.join("tools.json"))
+    }
+
+    fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn to_context(&self) -> String {
+        let mut context = String::new();
+
+        if let Some(distro) = &self.distro {
+            context.push_str(&format!("Distro: {}\n", distro));
+        }
+        if !self.installed.is_empty() {
+            context.push_str(&format!("Installed tools: {}\n", self.installed.join(", ")));
+        }
+        if !self.flavours.is_empty() {
+            let flavours: Vec<String> = self
+                .flavours
+                .iter()
+                .map(|tool| match &tool.version {
+                    Some(version) => format!("{} ({} {})", tool.name, tool.flavour, version),
+                    None => format!("{} ({})", tool.name, tool.flavour),
+                })
+                .collect();
+            context.push_str(&format!("Tool flavours: {}\n", flavours.join(", ")));
+        }
+
+        context
+    }
+}
+
+/// Current local date, time and timezone, so "last week" or "since Monday" resolve
+/// correctly. Never cached.
+pub fn date_context() -> String {
+    let now = Local::now();
+    let zone = timezone_name()
+        .map(|name| format!("{} (UTC{})", name, now.format("%:z")))
+        .unwrap_or_else(|| format!("UTC{}", now.format("%:z")));
+
+    format!(
+        "{} {} {}\n",
+        DATE_LINE_PREFIX,
+        now.format("%A %Y-%m-%d %H:%M"),
+        zone
+    )
+}
+
+/// `text` with the time of day removed from the line added by `date_context`.
+/// Cache keys use this so an entry lasts the day, but answers that mention
+/// "today" or "last week" aren't served on a later date.
+pub fn without_time_of_day(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| match line.strip_prefix(DATE_LINE_PREFIX) {
+            Some(rest) => {
+                let words: Vec<&str> = rest
+                    .split_whitespace()
+                    .filter(|word| !TIME_OF_DAY_REGEX.is_match(word))
+                    .collect();
+                let newline = if line.ends_with('\n') { "\n" } else { "" };
+                format!("{} {}{}", DATE_LINE_PREFIX, words.join(" "), newline)
+            }
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+/// IANA name from `$TZ` or the `/etc/localtime` symlink
+fn timezone_name() -> Option<String> {
+    if let Ok(tz) = env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() {
+            return Some(tz.to_string());
+        }
+    }
+
+    let target = fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    target
+        .split_once("zoneinfo/")
+        .map(|(_, name)| name.to_string())
+}
+
+fn distro() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        let output = Command::new("sw_vers")
+            .arg("-productVersion")
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Some(format!("macOS {}", version).trim().to_string());
+    }
+
+    ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| parse_os_release(&contents))
+}
+
+/// `PRETTY_NAME (ID VERSION_ID)` from an os-release file
+fn parse_os_release(contents: &str) -> Option<String> {
+    let field = |key: &str| {
+        contents.lines().find_map(|line| {
+            line.strip_prefix(key)?
+                .strip_prefix('=')
+                .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+        })
+    };
+
+    let id = field("ID")?;
+    let id_version = match field("VERSION_ID") {
+        Some(version) => format!("{id} {version}"),
+        None => id,
+    };
+
+    Some(match field("PRETTY_NAME") {
+        Some(pretty) => format!("{pretty} ({id_version})"),
+        None => id_version,
+    })
+}
+
+fn probe_flavour(tool: &str) -> ToolFlavour {
+    let output = Command::new(tool)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output();
+
+    let text = match output {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => String::new(),
+    };
+
+    classify_flavour(tool, &text)
+}
+
+/// Work out the flavour from `--version` output. BSD tools reject `--version`,
+/// so an unrecognised answer means BSD on macOS/BSD hosts.
+fn classify_flavour(tool: &str, version_output: &str) -> ToolFlavour {
+    let first_line = version_output.lines().next().unwrap_or_default();
+    let version = VERSION_REGEX
+        .find(first_line)
+        .map(|m| m.as_str().to_string());
+
+    let flavour =
+        if version_output.contains("GNU") || version_output.contains("Free Software Foundation") {
+            "GNU"
+        } else if version_output.contains("BusyBox") {
+            "BusyBox"
+        } else if version_output.contains("bsdtar") {
+            "BSD libarchive"
+        } else if version_output.contains("uutils") {
+            "uutils"
+        } else if cfg!(any(
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd"
+        )) {
+            "BSD"
+        } else {
+            "unknown"
+        };
+
+    ToolFlavour {
+        name: tool.to_string(),
+        flavour: flavour.to_string(),
+        version: if flavour == "BSD" { None } else { version },
+    }
+}
+
+fn find_in_path(tool: &str, path_var: &str) -> Option<PathBuf> {
+    env::split_paths(path_var)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_parse_os_release() {
+        let contents = "NAME=\"Ubuntu\"\nVERSION_ID=\"22.04\"\nID=ubuntu\nID_LIKE=debian\nPRETTY_NAME=\"Ubuntu 22.04.3 LTS\"\n";
+        assert_eq!(
+            parse_os_release(contents).as_deref(),
+            Some("Ubuntu 22.04.3 LTS (ubuntu 22.04)")
+        );
+
+        assert_eq!(parse_os_release("ID=alpine\n").as_deref(), Some("alpine"));
+        assert_eq!(parse_os_release("NAME=x\n"), None);
+    }
+
+    #[test]
+    fn test_classify_flavour() {
+        let gnu = classify_flavour(
+            "sed",
+            "sed (GNU sed) 4.9\nCopyright (C) 2022 Free Software Foundation, Inc.\n",
+        );
+        assert_eq!(gnu.flavour, "GNU");
+        assert_eq!(gnu.version.as_deref(), Some("4.9"));
+
+        let busybox = classify_flavour("find", "BusyBox v1.36.1 (2023-06-12) multi-call binary.\n");
+        assert_eq!(busybox.flavour, "BusyBox");
+        assert_eq!(busybox.version.as_deref(), Some("1.36.1"));
+
+        let bsdtar = classify_flavour("tar", "bsdtar 3.5.3 - libarchive 3.5.3 zlib/1.2.12\n");
+        assert_eq!(bsdtar.flavour, "BSD libarchive");
+    }
+
+    #[test]
+    fn test_inventory_context() {
+        let inventory = ToolInventory {
+            distro: Some("Debian GNU/Linux 12 (bookworm) (debian 12)".to_string()),
+            installed: vec!["git".to_string(), "rg".to_string()],
+            flavours: vec![ToolFlavour {
+                name: "sed".to_string(),
+                flavour: "GNU".to_string(),
+                version: Some("4.9".to_string()),
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            inventory.to_context(),
+            "Distro: Debian GNU/Linux 12 (bookworm) (debian 12)\n\
+             Installed tools: git, rg\n\
+             Tool flavours: sed (GNU 4.9)\n"
+        );
+    }
+
+    #[test]
+    fn test_without_time_of_day_keeps_the_date() {
+        let context = "OS: linux\nCurrent date and time: Saturday 2026-10-17 02:23 Europe/London (UTC+01:00)\nGit branch: main\n";
+        assert_eq!(
+            without_time_of_day(context),
+            "OS: linux\nCurrent date and time: Saturday 2026-10-17 Europe/London (UTC+01:00)\nGit branch: main\n"
+        );
+    }
+
+    #[test]
+    fn test_inventory_round_trips_through_cache_file() {
+        let dir = temp_dir();
+        let path = dir.path().join("tools.json");
+        let inventory = ToolInventory {
+            created_at: 42,
+            path: "/usr/bin".to_string(),
+            installed: vec!["jq".to_string()],
+            ..Default::default()
+        };
+
+        inventory.save(&path).unwrap();
+        assert_eq!(ToolInventory::load(&path), Some(inventory));
+    }
+}
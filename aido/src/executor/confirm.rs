@@ -1,6 +1,8 @@
+use crate::ai::Alternative;
 use crate::executor::validator::{RiskLevel, ValidationResult};
+use crate::ui::input::{prompt_user, read_terminal_line};
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use dialoguer::{theme::ColorfulTheme, Select};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConfirmAction {
@@ -8,6 +10,8 @@ pub enum ConfirmAction {
     Cancel,
     Edit,
     Explain,
+    /// Ask the model to change the command as described
+    Refine(String),
 }
 
 pub fn confirm_execution(
@@ -42,8 +46,7 @@ pub fn confirm_execution(
             "DELETE".red().bold()
         );
 
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
+        let input = read_terminal_line()?;
 
         if input.trim() != "DELETE" {
             return Ok(ConfirmAction::Cancel);
@@ -55,27 +58,82 @@ pub fn confirm_execution(
     // For other commands, show options menu
     println!("{}", "═".repeat(60).blue());
 
-    let options = vec!["Execute", "Cancel", "Explain what this does"];
+    let options = vec![
+        "Execute",
+        "Cancel",
+        "Explain what this does",
+        "Edit",
+        "Refine…",
+    ];
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("What would you like to do?")
-        .items(&options)
-        .default(0)
-        .interact()?;
+    loop {
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("What would you like to do?")
+            .items(&options)
+            .default(0)
+            .interact()?;
 
-    match selection {
-        0 => Ok(ConfirmAction::Execute),
-        1 => Ok(ConfirmAction::Cancel),
-        2 => Ok(ConfirmAction::Explain),
-        _ => Ok(ConfirmAction::Cancel),
+        return match selection {
+            0 => Ok(ConfirmAction::Execute),
+            1 => Ok(ConfirmAction::Cancel),
+            2 => Ok(ConfirmAction::Explain),
+            3 => Ok(ConfirmAction::Edit),
+            4 => {
+                let instruction = prompt_user("How should it change?")?;
+                if instruction.trim().is_empty() {
+                    continue;
+                }
+                Ok(ConfirmAction::Refine(instruction.trim().to_string()))
+            }
+            _ => Ok(ConfirmAction::Cancel),
+        };
     }
 }
 
-pub fn simple_confirm(message: &str) -> anyhow::Result<bool> {
-    let confirmation = Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt(message)
-        .default(false)
+/// Let the user pick one of several alternative plans (`aido do --alternatives`).
+/// `risks` holds each alternative's highest validator risk. `None` means cancel.
+pub fn choose_alternative(
+    alternatives: &[Alternative],
+    risks: &[RiskLevel],
+) -> anyhow::Result<Option<usize>> {
+    println!("\n{}", "═".repeat(60).blue());
+    println!("{}", "  Alternative Approaches".bold());
+    println!("{}", "═".repeat(60).blue());
+
+    for (i, (alternative, risk)) in alternatives.iter().zip(risks).enumerate() {
+        let risk = match risk {
+            RiskLevel::Critical => risk.label().red().bold(),
+            RiskLevel::High => risk.label().red(),
+            RiskLevel::Medium => risk.label().yellow(),
+            RiskLevel::Low => risk.label().green(),
+        };
+        println!("\n{}. [{}] {}", i + 1, risk, alternative.tradeoff);
+        for step in &alternative.plan.steps {
+            println!("   {} {}", "▶".cyan(), step.command.bright_white());
+        }
+    }
+    println!();
+
+    let mut items: Vec<String> = alternatives
+        .iter()
+        .zip(risks)
+        .enumerate()
+        .map(|(i, (alternative, risk))| {
+            let label = if alternative.tradeoff.is_empty() {
+                alternative.plan.steps[0].command.as_str()
+            } else {
+                alternative.tradeoff.as_str()
+            };
+            format!("{}. [{}] {}", i + 1, risk.label(), label)
+        })
+        .collect();
+    items.push("Cancel".to_string());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which approach?")
+        .items(&items)
+        .default(0)
         .interact()?;
 
-    Ok(confirmation)
+    Ok((selection < alternatives.len()).then_some(selection))
 }
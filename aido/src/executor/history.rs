@@ -0,0 +1,73 @@
+use crate::config::{data_dir, JsonLines};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One executed command. Text fields are redacted before they are written.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub at: DateTime<Utc>,
+    /// What the user asked for
+    pub request: String,
+    pub command: String,
+    pub exit_code: i32,
+    pub success: bool,
+    /// 1 for the model's first command, 2+ for revisions after a failure
+    pub attempt: u32,
+    /// The end of stderr, for failed commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The user changed the command with Edit before running it
+    #[serde(default)]
+    pub edited: bool,
+}
+
+impl HistoryRecord {
+    pub fn new(request: &str, command: &str, exit_code: i32, success: bool) -> Self {
+        Self {
+            at: Utc::now(),
+            request: request.to_string(),
+            command: command.to_string(),
+            exit_code,
+            success,
+            attempt: 1,
+            error: None,
+            edited: false,
+        }
+    }
+}
+
+/// Append-only JSON-lines log of executed commands at `<data dir>/history.jsonl`
+pub type CommandHistory = JsonLines<HistoryRecord>;
+
+impl CommandHistory {
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(data_dir()?.join("history.jsonl")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn test_append_and_read_back() {
+        let dir = temp_dir();
+        let path = dir.path().join("history.jsonl");
+        let history = CommandHistory::new(path.clone());
+
+        let mut failed = HistoryRecord::new("list files", "lss", 127, false);
+        failed.error = Some("lss: command not found".to_string());
+        let mut retried = HistoryRecord::new("list files", "ls -a", 0, true);
+        retried.attempt = 2;
+        retried.edited = true;
+
+        history.append(&failed).unwrap();
+        history.append(&retried).unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "not json\n").unwrap();
+
+        assert_eq!(history.records().unwrap(), [failed, retried]);
+    }
+}
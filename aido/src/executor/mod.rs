@@ -1,7 +1,9 @@
 pub mod confirm;
+pub mod history;
 pub mod shell;
 pub mod validator;
 
-pub use confirm::{confirm_execution, ConfirmAction};
+pub use confirm::{choose_alternative, confirm_execution, ConfirmAction};
+pub use history::{CommandHistory, HistoryRecord};
 pub use shell::ShellExecutor;
-pub use validator::SafetyValidator;
+pub use validator::{harden_probe, SafetyValidator};
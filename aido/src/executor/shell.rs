@@ -1,6 +1,11 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
+use std::io;
 use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::ui::input::terminal_stdin;
 
 #[derive(Debug)]
 pub struct ExecutionResult {
@@ -22,6 +27,10 @@ impl ShellExecutor {
         Self { shell, dry_run }
     }
 
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
     pub fn execute(&self, command: &str) -> Result<ExecutionResult> {
         if self.dry_run {
             println!("{} {}", "[DRY RUN]".yellow().bold(), command);
@@ -67,40 +76,109 @@ impl ShellExecutor {
         Ok(result)
     }
 
-    pub fn execute_sequence(&self, commands: Vec<String>) -> Result<Vec<ExecutionResult>> {
-        let mut results = Vec::new();
-
-        for (i, command) in commands.iter().enumerate() {
-            if commands.len() > 1 {
-                println!(
-                    "\n{} Command {} of {}",
-                    "━━━".blue().bold(),
-                    i + 1,
-                    commands.len()
-                );
+    /// Run a command and return its output without printing anything. Used for
+    /// read-only investigation probes, which run even in dry-run mode.
+    ///
+    /// At most `max_bytes` of stdout and of stderr are kept; a command that writes
+    /// more is killed. One still running after `time_limit` is killed too, and
+    /// reported as a failure. The command runs in its own process group, so
+    /// killing it also stops every process of a pipeline.
+    pub async fn capture(
+        &self,
+        command: &str,
+        max_bytes: usize,
+        time_limit: Duration,
+    ) -> Result<ExecutionResult> {
+        let mut probe = tokio::process::Command::new(&self.shell);
+        probe
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        probe.process_group(0);
+        let mut child = probe
+            .spawn()
+            .with_context(|| format!("Failed to execute command: {}", command))?;
+        let stdout = child.stdout.take().context("Failed to capture stdout")?;
+        let stderr = child.stderr.take().context("Failed to capture stderr")?;
+
+        let run = async {
+            let (stdout, stderr) = tokio::try_join!(
+                read_capped(stdout, max_bytes),
+                read_capped(stderr, max_bytes)
+            )?;
+            if stdout.len() >= max_bytes || stderr.len() >= max_bytes {
+                kill_tree(&mut child);
             }
+            let status = child.wait().await?;
+            Ok::<_, io::Error>(Output {
+                status,
+                stdout,
+                stderr,
+            })
+        };
 
-            match self.execute(command) {
-                Ok(result) => {
-                    let success = result.success;
-                    results.push(result);
-
-                    if !success {
-                        println!(
-                            "\n{} Stopping execution due to failure",
-                            "⚠".yellow().bold()
-                        );
-                        break;
-                    }
-                }
-                Err(e) => {
-                    println!("\n{} Error: {}", "✗".red().bold(), e);
-                    return Err(e);
-                }
+        match tokio::time::timeout(time_limit, run).await {
+            Ok(output) => Ok(self.process_output(
+                output.with_context(|| format!("Failed to execute command: {}", command))?,
+            )),
+            Err(_) => {
+                kill_tree(&mut child);
+                Ok(ExecutionResult {
+                    stdout: String::new(),
+                    stderr: format!("Timed out after {:?}", time_limit),
+                    exit_code: -1,
+                    success: false,
+                })
             }
         }
+    }
+
+    /// Run a command attached to the terminal, for editors, pagers and prompts.
+    /// Output isn't captured.
+    pub fn execute_interactive(&self, command: &str) -> Result<ExecutionResult> {
+        if self.dry_run {
+            println!("{} {}", "[DRY RUN]".yellow().bold(), command);
+            return Ok(ExecutionResult {
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: 0,
+                success: true,
+            });
+        }
+
+        println!("{} {}", "▶".cyan().bold(), command.bright_white());
+
+        let status = Command::new(&self.shell)
+            .arg("-c")
+            .arg(command)
+            .stdin(terminal_stdin())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("Failed to execute command: {}", command))?;
+
+        let result = ExecutionResult {
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: status.code().unwrap_or(-1),
+            success: status.success(),
+        };
+
+        if result.success {
+            println!("{}", "✓ Command completed successfully".green());
+        } else {
+            println!(
+                "{} Exit code: {}",
+                "✗ Command failed".red().bold(),
+                result.exit_code
+            );
+        }
 
-        Ok(results)
+        Ok(result)
     }
 
     fn process_output(&self, output: Output) -> ExecutionResult {
@@ -118,8 +196,91 @@ impl ShellExecutor {
     }
 }
 
+/// Kill a `capture` child and everything it started
+#[cfg(unix)]
+fn kill_tree(child: &mut tokio::process::Child) {
+    // The child leads its own process group, whose id is its pid
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) takes no pointers; a negative pid addresses the group
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_tree(child: &mut tokio::process::Child) {
+    child.start_kill().ok();
+}
+
+/// Read up to `max_bytes` from a pipe, then close it so the writer stops
+async fn read_capped(pipe: impl AsyncRead + Unpin, max_bytes: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    pipe.take(max_bytes as u64).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
 impl Default for ShellExecutor {
     fn default() -> Self {
         Self::new(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[tokio::test]
+    async fn test_capture_is_bounded() {
+        let executor = ShellExecutor::new(false);
+        let limit = Duration::from_secs(5);
+
+        let result = executor
+            .capture("echo hi; exit 3", 1024, limit)
+            .await
+            .unwrap();
+        assert_eq!((result.stdout.as_str(), result.exit_code), ("hi\n", 3));
+
+        let result = executor.capture("yes", 1024, limit).await.unwrap();
+        assert_eq!(result.stdout.len(), 1024);
+        assert!(!result.success);
+
+        let result = executor
+            .capture("sleep 5", 1024, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(result.stderr, "Timed out after 100ms");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_capture_kills_the_whole_pipeline() {
+        let dir = temp_dir();
+        let pid_file = dir.path().join("sleep.pid");
+        let command = format!(
+            "sh -c 'echo $$ > {}; exec sleep 30' | cat",
+            pid_file.display()
+        );
+
+        ShellExecutor::new(false)
+            .capture(&command, 1024, Duration::from_millis(300))
+            .await
+            .unwrap();
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Gone, or a zombie waiting for init to reap it
+        let state = Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(
+            state.trim().is_empty() || state.starts_with('Z'),
+            "sleep {} survived the timeout ({})",
+            pid.trim(),
+            state.trim()
+        );
+    }
+}
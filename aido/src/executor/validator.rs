@@ -32,7 +32,53 @@ lazy_static! {
     ];
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Commands an investigation probe may start with (`aido do --investigate`)
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "ls", "du", "df", "cat", "head", "tail", "wc", "stat", "file", "find", "grep", "rg", "tree",
+    "pwd", "which", "whoami", "uname", "ps", "sort", "uniq", "cut", "git",
+];
+
+/// Git subcommands that only read the repository
+const READ_ONLY_GIT_SUBCOMMANDS: &[&str] = &[
+    "status",
+    "log",
+    "diff",
+    "show",
+    "ls-files",
+    "rev-parse",
+    "remote",
+    "branch",
+];
+
+/// `find` actions that run or write something
+const FIND_WRITE_ACTIONS: &[&str] = &[
+    "-exec", "-execdir", "-ok", "-okdir", "-delete", "-fprint", "-fprint0", "-fprintf", "-fls",
+];
+
+/// Per-program argument prefixes that write files or run other programs
+const DENIED_PROBE_ARGS: &[(&str, &[&str])] = &[
+    // --pre, --pre=CMD and --pre-glob run a preprocessor on every file
+    ("rg", &["--pre"]),
+    ("tree", &["-o"]),
+    ("sort", &["--output", "--compress-program"]),
+    ("file", &["--compile"]),
+    ("git", &["--output", "--ext-diff", "--textconv"]),
+];
+
+/// Short flags that write a file, even inside a cluster like `sort -uo out`
+const DENIED_SHORT_FLAGS: &[(&str, char)] = &[("sort", 'o'), ("tree", 'o'), ("file", 'C')];
+
+/// `uniq` options that take a separate value
+const UNIQ_VALUE_OPTIONS: &[&str] = &[
+    "-f",
+    "-s",
+    "-w",
+    "--skip-fields",
+    "--skip-chars",
+    "--check-chars",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RiskLevel {
     Low,
     Medium,
@@ -40,6 +86,17 @@ pub enum RiskLevel {
     Critical,
 }
 
+impl RiskLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+            RiskLevel::Critical => "critical",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidationResult {
     pub is_safe: bool,
@@ -82,9 +139,7 @@ impl SafetyValidator {
         if needs_confirmation || is_in_dangerous_list {
             return ValidationResult {
                 is_safe: true,
-                risk_level: if first_word == "sudo" {
-                    RiskLevel::High
-                } else if first_word == "rm" {
+                risk_level: if first_word == "sudo" || first_word == "rm" {
                     RiskLevel::High
                 } else {
                     RiskLevel::Medium
@@ -103,6 +158,44 @@ impl SafetyValidator {
         }
     }
 
+    /// The stricter policy for investigation probes, which run without
+    /// confirmation: a read-only command from a fixed list, optionally piped into
+    /// others, with no redirection, chaining or command substitution.
+    pub fn validate_probe(&self, command: &str) -> ValidationResult {
+        let refused = |reason: String| ValidationResult {
+            is_safe: false,
+            risk_level: RiskLevel::Medium,
+            warning: Some(reason),
+            requires_confirmation: true,
+        };
+
+        let validation = self.validate(command);
+        if validation.risk_level != RiskLevel::Low {
+            return refused(
+                validation
+                    .warning
+                    .unwrap_or_else(|| "Not a read-only command".to_string()),
+            );
+        }
+
+        let Some(segments) = split_pipeline(command) else {
+            return refused(
+                "Probes can't use redirection, chaining or command substitution".to_string(),
+            );
+        };
+        for segment in segments {
+            let words = match shell_words::split(&segment) {
+                Ok(words) if !words.is_empty() => words,
+                _ => return refused(format!("Can't parse probe: {}", segment.trim())),
+            };
+            if let Err(reason) = check_read_only(&words) {
+                return refused(reason);
+            }
+        }
+
+        validation
+    }
+
     fn get_critical_warning(&self, command: &str) -> String {
         if command.contains("rm") && command.contains("-rf") {
             "This command will PERMANENTLY DELETE files/directories recursively!".to_string()
@@ -138,6 +231,153 @@ impl SafetyValidator {
     }
 }
 
+/// Split a command on unquoted `|`. `None` when it uses anything else that could
+/// write or run more than the listed commands: `;`, `&`, `>`, `<`, `||`, newlines,
+/// backticks or `$(`.
+fn split_pipeline(command: &str) -> Option<Vec<String>> {
+    let mut segments = vec![String::new()];
+    let (mut single, mut double) = (false, false);
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if !double => single = !single,
+            '"' if !single => double = !double,
+            '\\' if !single => {
+                segments.last_mut()?.push(c);
+                if let Some(escaped) = chars.next() {
+                    segments.last_mut()?.push(escaped);
+                }
+                continue;
+            }
+            '`' if !single => return None,
+            '$' if !single && chars.peek() == Some(&'(') => return None,
+            '|' if !single && !double => {
+                if chars.peek() == Some(&'|') {
+                    return None;
+                }
+                segments.push(String::new());
+                continue;
+            }
+            ';' | '&' | '>' | '<' | '\n' if !single && !double => return None,
+            _ => {}
+        }
+        segments.last_mut()?.push(c);
+    }
+
+    (!single && !double).then_some(segments)
+}
+
+/// One pipeline segment, already split into words
+fn check_read_only(words: &[String]) -> Result<(), String> {
+    let program = words[0].as_str();
+    let args = &words[1..];
+
+    if !READ_ONLY_COMMANDS.contains(&program) {
+        return Err(format!("{program} is not on the read-only probe list"));
+    }
+
+    let denied = DENIED_PROBE_ARGS
+        .iter()
+        .filter(|(name, _)| *name == program)
+        .flat_map(|(_, prefixes)| prefixes.iter());
+    for prefix in denied {
+        if let Some(arg) = args.iter().find(|arg| arg.starts_with(prefix)) {
+            return Err(format!("{program} {arg} is not allowed in a probe"));
+        }
+    }
+
+    let denied = DENIED_SHORT_FLAGS
+        .iter()
+        .filter(|(name, _)| *name == program)
+        .map(|(_, flag)| *flag);
+    for flag in denied {
+        if args
+            .iter()
+            .any(|arg| !arg.starts_with("--") && arg.starts_with('-') && arg.contains(flag))
+        {
+            return Err(format!("{program} -{flag} writes a file"));
+        }
+    }
+
+    match program {
+        "find" => {
+            if let Some(action) = args
+                .iter()
+                .find(|arg| FIND_WRITE_ACTIONS.contains(&arg.as_str()))
+            {
+                return Err(format!("find {action} is not allowed in a probe"));
+            }
+        }
+        // `uniq INPUT OUTPUT` writes OUTPUT
+        "uniq" => {
+            let mut operands = 0;
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                if UNIQ_VALUE_OPTIONS.contains(&arg.as_str()) {
+                    args.next();
+                } else if arg == "-" || !arg.starts_with('-') {
+                    operands += 1;
+                }
+            }
+            if operands > 1 {
+                return Err("uniq with an output file writes it".to_string());
+            }
+        }
+        "git" => {
+            let subcommand = args.first().map(String::as_str).unwrap_or("");
+            if !READ_ONLY_GIT_SUBCOMMANDS.contains(&subcommand) {
+                return Err(format!("git {subcommand} is not a read-only git command"));
+            }
+            let writes = match subcommand {
+                // Listing only: `git branch`, `git branch -a`, `git remote -v`
+                "branch" | "remote" => args[1..].iter().any(|arg| {
+                    !matches!(
+                        arg.as_str(),
+                        "-a" | "-r" | "-v" | "-vv" | "--all" | "--list" | "--show-current"
+                    )
+                }),
+                _ => false,
+            };
+            if writes {
+                return Err(format!("Only listing is allowed with git {subcommand}"));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Rewrite a probe that passed `validate_probe` so `git` can't run programs named
+/// in repository config: the fsmonitor hook, an external diff or textconv filters.
+/// Other pipeline segments are left as written.
+pub fn harden_probe(command: &str) -> String {
+    let Some(segments) = split_pipeline(command) else {
+        return command.to_string();
+    };
+
+    segments
+        .iter()
+        .map(|segment| match shell_words::split(segment) {
+            Ok(words) if words.first().map(String::as_str) == Some("git") => {
+                let mut hardened = vec!["git", "-c", "core.fsmonitor=false"];
+                let subcommand = words.get(1).map(String::as_str).unwrap_or("");
+                hardened.push(subcommand);
+                if matches!(subcommand, "diff" | "show" | "log") {
+                    hardened.extend(["--no-ext-diff", "--no-textconv"]);
+                }
+                hardened.extend(words.iter().skip(2).map(String::as_str));
+                format!(" {} ", shell_words::join(hardened))
+            }
+            _ => segment.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+        .trim()
+        .to_string()
+}
+
 impl Default for SafetyValidator {
     fn default() -> Self {
         Self::new(vec![
@@ -181,4 +421,71 @@ mod tests {
         assert!(!result.requires_confirmation);
         assert_eq!(result.risk_level, RiskLevel::Low);
     }
+
+    #[test]
+    fn test_probe_policy() {
+        let validator = SafetyValidator::default();
+
+        for probe in [
+            "ls -la /var/log",
+            "du -sh /var/log/* | sort -h | tail -5",
+            "git status --short",
+            "git branch -a",
+            "sort words.txt | uniq -c | sort -rn",
+            "uniq -f 1 data.txt",
+            "file -b Cargo.toml",
+            "grep -rn 'a|b;c' src",
+            "find . -name '*.log' -size +10M",
+        ] {
+            let result = validator.validate_probe(probe);
+            assert!(result.is_safe, "{probe}: {:?}", result.warning);
+        }
+
+        for probe in [
+            "rm -rf build",
+            "ls > files.txt",
+            "ls; rm x",
+            "cat $(which aido)",
+            "ls `pwd`",
+            "ls && touch x",
+            "find . -name '*.tmp' -delete",
+            "sort -o out.txt in.txt",
+            "sort -uo out.txt in.txt",
+            "sort --compress-program=sh -S 1 big.txt",
+            "rg --pre cat pattern",
+            "rg --pre=./run.sh pattern",
+            "rg --pre-glob '*.gz' pattern",
+            "tree -o tree.txt",
+            "git diff --ext-diff",
+            "git log -p --textconv",
+            "git log --output=log.txt",
+            "uniq in.txt out.txt",
+            "sort in.txt | uniq -c - counts.txt",
+            "file -C -m magic",
+            "file -bC",
+            "git checkout main",
+            "git branch -D old",
+            "curl https://example.com",
+            "sudo ls /root",
+            "ls 'unterminated",
+        ] {
+            assert!(!validator.validate_probe(probe).is_safe, "{probe}");
+        }
+    }
+
+    #[test]
+    fn test_harden_probe() {
+        assert_eq!(
+            harden_probe("git diff --stat | tail -1"),
+            "git -c 'core.fsmonitor=false' diff --no-ext-diff --no-textconv --stat | tail -1"
+        );
+        assert_eq!(
+            harden_probe("git status --short"),
+            "git -c 'core.fsmonitor=false' status --short"
+        );
+        assert_eq!(
+            harden_probe("du -sh /var/log/* | sort -h"),
+            "du -sh /var/log/* | sort -h"
+        );
+    }
 }
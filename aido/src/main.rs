@@ -1,12 +1,18 @@
 mod ai;
 mod config;
+mod context;
 mod executor;
 mod modes;
+#[cfg(test)]
+mod test_support;
 mod ui;
 
-use crate::config::{binding_label, binding_to_bash, binding_to_fish, binding_to_zsh};
+use crate::ai::prompts::PromptKind;
+use crate::config::{binding_label, binding_to_bash, binding_to_fish, binding_to_zsh, BackendKind};
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use colored::Colorize;
+use std::path::PathBuf;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
@@ -20,6 +26,26 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Show tokens used and cost after each AI response
+    #[arg(long, global = true)]
+    usage: bool,
+
+    /// Record every AI prompt and response to a cassette file
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer from a recorded cassette file instead of calling the model
+    #[arg(long, global = true, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Don't add the project's AIDO.md instructions to prompts
+    #[arg(long, global = true)]
+    no_project_instructions: bool,
+
+    /// Don't read piped input from stdin
+    #[arg(long, global = true)]
+    no_stdin: bool,
 }
 
 #[derive(Subcommand)]
@@ -37,6 +63,31 @@ enum Commands {
         /// Show what would be executed without running it
         #[arg(short = 'n', long)]
         dry_run: bool,
+
+        /// After a command fails, ask for a revised one up to N times
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retry_on_failure: u32,
+
+        /// Let the AI run read-only commands to look around before planning
+        #[arg(long)]
+        investigate: bool,
+
+        /// Offer N different approaches and pick one (2-5)
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u32).range(2..=5),
+            conflicts_with = "investigate"
+        )]
+        alternatives: Option<u32>,
+
+        /// Ignore the response cache and always ask the model
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Attach a file to the prompt (repeatable; globs allowed)
+        #[arg(short = 'f', long = "file", value_name = "PATH")]
+        files: Vec<String>,
     },
 
     /// Ask questions and get answers (ASK mode)
@@ -44,6 +95,50 @@ enum Commands {
         /// The question to ask
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         question: Vec<String>,
+
+        /// Follow up on the most recent session
+        #[arg(short = 'c', long = "continue", conflicts_with = "session")]
+        continue_last: bool,
+
+        /// Ask within a named session, creating it if needed
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Attach a file to the prompt (repeatable; globs allowed)
+        #[arg(short = 'f', long = "file", value_name = "PATH")]
+        files: Vec<String>,
+    },
+
+    /// Suggest a corrected version of a command that just failed
+    Fix {
+        /// The failed command (defaults to the last command recorded by the shell hooks,
+        /// which record only the command and its exit code, not its error output)
+        #[arg(long)]
+        cmd: Option<String>,
+
+        /// Its exit code
+        #[arg(long, allow_hyphen_values = true)]
+        exit_code: Option<i32>,
+
+        /// Its error output (piped stdin is used when this is omitted)
+        #[arg(long)]
+        stderr: Option<String>,
+
+        /// Skip confirmation and execute immediately
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Show what would be executed without running it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// After a command fails, ask for a revised one up to N times
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retry_on_failure: u32,
+
+        /// Ignore the response cache and always ask the model
+        #[arg(long)]
+        no_cache: bool,
     },
 
     /// Initialize configuration
@@ -71,7 +166,8 @@ enum Commands {
     /// Check configuration and dependencies
     Doctor,
 
-    /// Generate shell integration code for keybindings
+    /// Generate shell integration code for keybindings. The hooks also record the
+    /// last command and its exit code for `aido fix`, but not its error output
     SetupShell,
 
     /// Manage persistent unsafe execution mode
@@ -79,6 +175,38 @@ enum Commands {
         #[command(subcommand)]
         action: UnsafeAction,
     },
+
+    /// Inspect and customise the prompt templates
+    Prompts {
+        #[command(subcommand)]
+        action: PromptsAction,
+    },
+
+    /// Manage saved ASK conversations
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+
+    /// Print the context that would be sent with a prompt
+    Context {
+        /// Which prompt to gather context for
+        #[arg(value_enum, default_value = "do")]
+        mode: PromptKind,
+    },
+
+    /// Report tokens and cost by day, mode and model
+    Usage {
+        /// Number of days to include, counting today
+        #[arg(short, long, default_value = "7")]
+        days: u32,
+    },
+
+    /// Manage the local response cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -111,6 +239,56 @@ enum UnsafeAction {
     Status,
 }
 
+#[derive(Subcommand)]
+enum PromptsAction {
+    /// Print the template in use
+    Show {
+        #[arg(value_enum)]
+        name: PromptKind,
+
+        /// Print the built-in default even if it is overridden
+        #[arg(long)]
+        default: bool,
+    },
+
+    /// Open a template in $EDITOR, starting from the built-in default
+    Edit {
+        #[arg(value_enum)]
+        name: PromptKind,
+    },
+
+    /// Remove overrides and go back to the built-in defaults
+    Reset {
+        /// Template to reset (all of them if omitted)
+        #[arg(value_enum)]
+        name: Option<PromptKind>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// List saved sessions, most recent first
+    List,
+
+    /// Show every turn of a session
+    Show {
+        /// Session name
+        name: String,
+    },
+
+    /// Delete a session
+    Delete {
+        /// Session name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Delete all cached responses
+    Clear,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -135,13 +313,85 @@ async fn main() -> Result<()> {
             prompt,
             yes,
             dry_run,
+            retry_on_failure,
+            investigate,
+            alternatives,
+            no_cache,
+            files,
         }) => {
             let prompt_str = prompt.join(" ");
-            modes::do_mode(&prompt_str, yes, dry_run).await?;
+            let options = ai::ClientOptions {
+                no_cache,
+                show_usage: cli.usage,
+                record: cli.record,
+                replay: cli.replay,
+                no_project_instructions: cli.no_project_instructions,
+            };
+            let attachments = attachments(cli.no_stdin, &files)?;
+            let do_options = modes::DoOptions {
+                skip_confirmation: yes,
+                dry_run,
+                retry_on_failure,
+                investigate,
+                alternatives,
+            };
+            modes::do_mode(&prompt_str, &attachments, &do_options, &options).await?;
         }
-        Some(Commands::Ask { question }) => {
+        Some(Commands::Ask {
+            question,
+            continue_last,
+            session,
+            files,
+        }) => {
             let question_str = question.join(" ");
-            modes::ask_mode(&question_str).await?;
+            let options = ai::ClientOptions {
+                show_usage: cli.usage,
+                record: cli.record,
+                replay: cli.replay,
+                no_project_instructions: cli.no_project_instructions,
+                ..Default::default()
+            };
+            let attachments = attachments(cli.no_stdin, &files)?;
+            modes::ask_mode(
+                &question_str,
+                continue_last,
+                session.as_deref(),
+                &attachments,
+                &options,
+            )
+            .await?;
+        }
+        Some(Commands::Fix {
+            cmd,
+            exit_code,
+            stderr,
+            yes,
+            dry_run,
+            retry_on_failure,
+            no_cache,
+        }) => {
+            let max_bytes = config::load_config()?.context.stdin_max_bytes;
+            let piped = if cli.no_stdin {
+                None
+            } else {
+                context::Attachment::from_stdin(max_bytes)?
+            };
+            let failure = modes::FailedCommand::resolve(cmd, exit_code, stderr, piped, max_bytes)?;
+            let options = ai::ClientOptions {
+                no_cache,
+                show_usage: cli.usage,
+                record: cli.record,
+                replay: cli.replay,
+                no_project_instructions: cli.no_project_instructions,
+            };
+            let do_options = modes::DoOptions {
+                skip_confirmation: yes,
+                dry_run,
+                retry_on_failure,
+                investigate: false,
+                alternatives: None,
+            };
+            modes::fix_mode(&failure, &do_options, &options).await?;
         }
         Some(Commands::Init) => {
             config::init_config()?;
@@ -150,7 +400,7 @@ async fn main() -> Result<()> {
             match action {
                 ConfigAction::Show => {
                     let cfg = config::load_config()?;
-                    println!("{}", serde_json::to_string_pretty(&cfg)?);
+                    println!("{}", serde_json::to_string_pretty(&cfg.for_display())?);
                 }
                 ConfigAction::Edit => {
                     let config_path = dirs::config_dir()
@@ -172,8 +422,44 @@ async fn main() -> Result<()> {
             }
         }
         Some(Commands::History { limit }) => {
-            println!("Showing last {limit} commands...");
-            // TODO: Implement history
+            let records = executor::CommandHistory::open()?.records()?;
+            if records.is_empty() {
+                println!("No commands have been executed yet.");
+            }
+            let start = records.len().saturating_sub(limit);
+            for (i, record) in records.iter().enumerate().skip(start) {
+                let status = if record.success {
+                    "✓".green().to_string()
+                } else {
+                    format!("{} {}", "✗".red(), record.exit_code)
+                };
+                let mut notes = Vec::new();
+                if record.attempt > 1 {
+                    notes.push(format!("attempt {}", record.attempt));
+                }
+                if record.edited {
+                    notes.push("edited".to_string());
+                }
+                let notes = if notes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", notes.join(", "))
+                };
+                println!(
+                    "{:>4}  {}  {}  {}{}",
+                    i + 1,
+                    record
+                        .at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M"),
+                    status,
+                    record.command,
+                    notes.dimmed()
+                );
+                if record.attempt == 1 {
+                    println!("      {}", record.request.dimmed());
+                }
+            }
         }
         Some(Commands::Rerun { index }) => {
             println!("Re-running command #{index}...");
@@ -187,24 +473,52 @@ async fn main() -> Result<()> {
                 Ok(cfg) => {
                     println!("✓ Configuration loaded successfully");
 
-                    // Check if claude CLI is available
-                    let claude_check = std::process::Command::new(&cfg.ai.claude_command)
-                        .arg("--version")
-                        .output();
-
-                    match claude_check {
-                        Ok(output) if output.status.success() => {
-                            println!("✓ Claude CLI found: {}", cfg.ai.claude_command);
+                    println!("✓ Backend: {}", cfg.ai.backend);
+
+                    match cfg.ai.backend {
+                        BackendKind::Cli => {
+                            // Check if claude CLI is available
+                            let claude_check = std::process::Command::new(&cfg.ai.claude_command)
+                                .arg("--version")
+                                .output();
+
+                            match claude_check {
+                                Ok(output) if output.status.success() => {
+                                    println!("✓ Claude CLI found: {}", cfg.ai.claude_command);
+                                }
+                                _ => {
+                                    println!("✗ Claude CLI not found!");
+                                    println!(
+                                        "  Install Claude Code from: https://docs.claude.com/claude-code"
+                                    );
+                                }
+                            }
+                        }
+                        BackendKind::Api => {
+                            let key_set = std::env::var(&cfg.ai.api_key_env)
+                                .map(|key| !key.trim().is_empty())
+                                .unwrap_or(false);
+
+                            if key_set {
+                                println!("✓ API key found in ${}", cfg.ai.api_key_env);
+                            } else {
+                                println!("✗ API key missing: ${} is not set", cfg.ai.api_key_env);
+                            }
+                            println!("✓ API endpoint: {}", cfg.ai.api_base_url);
                         }
-                        _ => {
-                            println!("✗ Claude CLI not found!");
-                            println!(
-                                "  Install Claude Code from: https://docs.claude.com/claude-code"
-                            );
+                        BackendKind::OpenAi => {
+                            println!("✓ Endpoint: {}", cfg.ai.openai_base_url);
+                            if cfg.ai.openai_model.trim().is_empty() {
+                                println!("✗ ai.openai_model is not set");
+                            }
                         }
                     }
 
-                    println!("✓ Model: {}", cfg.ai.model);
+                    if cfg.ai.backend == BackendKind::OpenAi {
+                        println!("✓ Model: {}", cfg.ai.openai_model);
+                    } else {
+                        println!("✓ Model: {}", cfg.ai.model);
+                    }
                 }
                 Err(e) => {
                     println!("✗ Configuration error: {e}");
@@ -215,6 +529,47 @@ async fn main() -> Result<()> {
             let shell = std::env::var("SHELL").unwrap_or_else(|_| "unknown".to_string());
             println!("✓ Shell: {shell}");
 
+            // Check project instructions
+            match ai::instructions::ProjectInstructions::discover() {
+                Ok(Some(instructions)) if cli.no_project_instructions => println!(
+                    "- Project instructions: {} (disabled by --no-project-instructions)",
+                    instructions.path.display()
+                ),
+                Ok(Some(instructions)) => {
+                    println!(
+                        "✓ Project instructions: {} ({} bytes)",
+                        instructions.path.display(),
+                        instructions.text.len()
+                    );
+                    if instructions.truncated {
+                        println!(
+                            "⚠ Project instructions truncated to {} bytes",
+                            ai::instructions::MAX_INSTRUCTIONS_BYTES
+                        );
+                    }
+                }
+                Ok(None) => println!("- Project instructions: none (no AIDO.md found)"),
+                Err(e) => println!("✗ Project instructions: {e}"),
+            }
+
+            // Check shell history context
+            let cfg = config::load_config().unwrap_or_default();
+            if !cfg.context.history {
+                println!("- Shell history context: off (enable with [context] history = true)");
+            } else {
+                match context::ShellHistory::load(&cfg.context) {
+                    Ok(Some(history)) => println!(
+                        "✓ Shell history context: {} ({} recent commands)",
+                        history.path.display(),
+                        history.commands.len()
+                    ),
+                    Ok(None) => {
+                        println!("⚠ Shell history context: no history file found for this shell")
+                    }
+                    Err(e) => println!("✗ Shell history context: {e}"),
+                }
+            }
+
             println!("\nAll checks complete!");
         }
         Some(Commands::SetupShell) => {
@@ -266,6 +621,24 @@ zle -N aido-ask-widget
 
 bindkey '{ask_bind}' aido-ask-widget     # {ask_label} for ASK mode
 bindkey '{do_bind}' aido-do-widget       # {do_label} for DO mode
+
+# Remember the last command and its exit status for `aido fix`
+function _aido_preexec() {{
+    _aido_last_command="$1"
+}}
+
+function _aido_precmd() {{
+    local last_status=$?
+    if [[ -n "$_aido_last_command" ]]; then
+        export AIDO_LAST_COMMAND="$_aido_last_command"
+        export AIDO_LAST_STATUS="$last_status"
+        unset _aido_last_command
+    fi
+}}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec _aido_preexec
+add-zsh-hook precmd _aido_precmd
 "#,
                     ask_label = ask_label,
                     do_label = do_label,
@@ -300,6 +673,16 @@ function aido-ask-widget() {{
 
 bind -x '"{ask_bind}": aido-ask-widget'     # {ask_label} for ASK mode
 bind -x '"{do_bind}": aido-do-widget'       # {do_label} for DO mode
+
+# Remember the last command and its exit status for `aido fix`
+function _aido_prompt_hook() {{
+    local last_status=$?
+    export AIDO_LAST_COMMAND="$(HISTTIMEFORMAT= history 1 | sed 's/^ *[0-9]* *//')"
+    export AIDO_LAST_STATUS="$last_status"
+    return $last_status
+}}
+
+PROMPT_COMMAND="_aido_prompt_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
 "#,
                     ask_label = ask_label,
                     do_label = do_label,
@@ -336,6 +719,12 @@ end
 
 bind {ask_bind} aido_ask_widget       # {ask_label} for ASK mode
 bind {do_bind} aido_do_widget         # {do_label} for DO mode
+
+# Remember the last command and its exit status for `aido fix`
+function _aido_postexec --on-event fish_postexec
+    set -gx AIDO_LAST_STATUS $status
+    set -gx AIDO_LAST_COMMAND $argv[1]
+end
 "#,
                     ask_label = ask_label,
                     do_label = do_label,
@@ -379,6 +768,181 @@ bind {do_bind} aido_do_widget         # {do_label} for DO mode
                 }
             }
         },
+        Some(Commands::Prompts { action }) => match action {
+            PromptsAction::Show { name, default } => {
+                let path = name.override_path()?;
+                if !default && path.exists() {
+                    println!("{}", format!("# {}", path.display()).dimmed());
+                    println!("{}", name.template());
+                } else {
+                    println!(
+                        "{}",
+                        format!("# built-in {} template", name.name()).dimmed()
+                    );
+                    println!("{}", name.default_template());
+                }
+            }
+            PromptsAction::Edit { name } => {
+                let path = name.override_path()?;
+                if !path.exists() {
+                    if let Some(dir) = path.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    std::fs::write(&path, name.default_template())?;
+                }
+
+                let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
+                std::process::Command::new(editor).arg(&path).status()?;
+            }
+            PromptsAction::Reset { name } => {
+                let kinds = name
+                    .map(|kind| vec![kind])
+                    .unwrap_or(PromptKind::ALL.to_vec());
+                let mut restored = 0;
+                for kind in kinds {
+                    let path = kind.override_path()?;
+                    if path.exists() {
+                        std::fs::remove_file(&path)?;
+                        restored += 1;
+                        ui::display::print_success(&format!(
+                            "Restored the built-in {} template",
+                            kind.name()
+                        ));
+                    }
+                }
+                if restored == 0 {
+                    println!("Already using the built-in template(s).");
+                }
+            }
+        },
+        Some(Commands::Sessions { action }) => {
+            let store = ai::session::SessionStore::open()?;
+            match action {
+                SessionsAction::List => {
+                    let sessions = store.list()?;
+                    if sessions.is_empty() {
+                        println!("No saved sessions.");
+                    }
+                    for session in sessions {
+                        let first = session
+                            .turns
+                            .first()
+                            .map(|turn| turn.question.chars().take(50).collect::<String>())
+                            .unwrap_or_default();
+                        println!(
+                            "{:<24} {:>3} turn(s)  {}  {}",
+                            session.name,
+                            session.turns.len(),
+                            session
+                                .updated_at()
+                                .with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M"),
+                            first
+                        );
+                    }
+                }
+                SessionsAction::Show { name } => {
+                    let session = store
+                        .load(&name)?
+                        .ok_or_else(|| anyhow::anyhow!("No session named '{name}'"))?;
+                    for turn in &session.turns {
+                        println!("\n{} {}", "Q:".bold(), turn.question.bold());
+                        println!(
+                            "{}",
+                            format!(
+                                "{} · {}",
+                                turn.asked_at
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M"),
+                                turn.model
+                            )
+                            .dimmed()
+                        );
+                        println!("\n{}", turn.answer);
+                    }
+                    println!();
+                }
+                SessionsAction::Delete { name } => {
+                    if store.delete(&name)? {
+                        ui::display::print_success(&format!("Deleted session {name}"));
+                    } else {
+                        anyhow::bail!("No session named '{name}'");
+                    }
+                }
+            }
+        }
+        Some(Commands::Context { mode }) => {
+            let cfg = config::load_config()?;
+            let redactor = ai::redact::Redactor::from_config(&cfg.redact);
+            let context =
+                context::PromptContext::for_prompt(&cfg.context, mode).redacted(&redactor);
+            if context.is_empty() {
+                println!(
+                    "No context is sent with {} prompts (all providers are disabled or the template has no {{context}} placeholder)",
+                    mode.name()
+                );
+            } else {
+                print!("{}", context.render());
+            }
+        }
+        Some(Commands::Usage { days }) => {
+            let log = ai::usage::UsageLog::open()?;
+            let today = chrono::Local::now().date_naive();
+            let since = today - chrono::Days::new(u64::from(days.max(1) - 1));
+            let records: Vec<_> = log
+                .records()?
+                .into_iter()
+                .filter(|record| record.day() >= since)
+                .collect();
+
+            if records.is_empty() {
+                println!("No AI usage recorded in the last {days} day(s).");
+            } else {
+                println!(
+                    "{:<10}  {:<8}  {:<28}  {:>5}  {:>9}  {:>9}  {:>9}",
+                    "Day", "Mode", "Model", "Calls", "Input", "Output", "Cost"
+                );
+                let (mut calls, mut cost) = (0, 0.0);
+                for row in ai::usage::summarize(&records) {
+                    // Self-hosted backends don't report a price
+                    let row_cost = if row.unpriced_calls == row.calls {
+                        "-".to_string()
+                    } else {
+                        format!("${:.4}", row.cost_usd)
+                    };
+                    println!(
+                        "{:<10}  {:<8}  {:<28}  {:>5}  {:>9}  {:>9}  {:>9}",
+                        row.day.to_string(),
+                        row.mode,
+                        row.model,
+                        row.calls,
+                        row.input_tokens,
+                        row.output_tokens,
+                        row_cost
+                    );
+                    calls += row.calls;
+                    cost += row.cost_usd;
+                }
+                println!("\n{calls} call(s), ${cost:.4} in the last {days} day(s)");
+            }
+
+            let budget = config::load_config()?.ai.daily_budget;
+            if let Some(budget) = budget {
+                println!(
+                    "Today: ${:.4} of ${:.2} daily budget",
+                    log.spent_on(today)?,
+                    budget
+                );
+            }
+        }
+        Some(Commands::Cache { action }) => match action {
+            CacheAction::Clear => {
+                let cache =
+                    ai::cache::ResponseCache::new(ai::cache::ResponseCache::default_dir()?, 0);
+                let removed = cache.clear()?;
+                ui::display::print_success(&format!("Removed {removed} cached response(s)"));
+            }
+        },
         None => {
             ui::settings::open_settings()?;
         }
@@ -386,3 +950,20 @@ bind {do_bind} aido_do_widget         # {do_label} for DO mode
 
     Ok(())
 }
+
+/// Data piped into `aido do` / `aido ask` (unless `--no-stdin` was given),
+/// followed by any `--file` attachments
+fn attachments(no_stdin: bool, files: &[String]) -> Result<Vec<context::Attachment>> {
+    let limits = config::load_config()?.context;
+    let mut attachments = Vec::new();
+
+    if !no_stdin {
+        attachments.extend(context::Attachment::from_stdin(limits.stdin_max_bytes)?);
+    }
+    attachments.extend(context::Attachment::from_files(
+        files,
+        limits.file_max_bytes,
+    )?);
+
+    Ok(attachments)
+}
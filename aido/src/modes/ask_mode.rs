@@ -1,28 +1,157 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::io::Write;
 
-use crate::ai::ClaudeClient;
+use crate::ai::prompts::PromptKind;
+use crate::ai::session::{Session, SessionStore};
+use crate::ai::{AiError, ClaudeClient, ClientOptions};
 use crate::config::load_config;
-use crate::ui::display::print_info;
+use crate::context::{with_attachments, Attachment, PromptContext};
+use crate::ui::display::{
+    print_cancelled, print_context, print_fallback_model, print_info, print_usage,
+};
 
-pub async fn ask_mode(question: &str) -> Result<()> {
+pub async fn ask_mode(
+    question: &str,
+    continue_last: bool,
+    session_name: Option<&str>,
+    attachments: &[Attachment],
+    options: &ClientOptions,
+) -> Result<()> {
     print_info(&format!("Asking: {}", question.italic()));
+    for attachment in attachments {
+        print_info(&format!("Attached {}", attachment.summary()));
+    }
+    let question = &with_attachments(question, attachments);
 
     // Load config
     let config = load_config()?;
 
+    // Pick up the conversation this question belongs to
+    let store = SessionStore::open()?;
+    let mut session = resolve_session(&store, continue_last, session_name)?;
+    let history = session.context_turns();
+    if !history.is_empty() {
+        print_info(&format!(
+            "Continuing session {} ({} earlier turn(s))",
+            session.name.bold(),
+            session.turns.len()
+        ));
+    }
+
     // Create AI client
-    let client = ClaudeClient::from_config(&config)?;
+    let client = ClaudeClient::from_config(&config, options)?;
 
-    // Get answer
-    let answer = client.answer_question(question).await?;
+    let context =
+        PromptContext::for_prompt(&config.context, PromptKind::Ask).redacted(client.redactor());
+    if config.ui.show_context {
+        print_context(&context);
+    }
+    let context = context.render();
 
     // Display answer
     println!("\n{}", "═".repeat(60).blue());
     println!("{}", "Answer".bold());
     println!("{}", "═".repeat(60).blue());
-    println!("\n{}", answer);
     println!();
 
+    if config.ai.stream {
+        let streamed = client
+            .stream_answer(question, &history, &context, &mut |text| {
+                print!("{}", text);
+                std::io::stdout().flush().ok();
+            })
+            .await;
+
+        match streamed {
+            Ok(response) => {
+                println!("\n");
+                if response.model != client.primary_model() {
+                    print_fallback_model(&response.model);
+                }
+                if options.show_usage {
+                    print_usage(&response);
+                }
+                let redactor = client.redactor();
+                session.record(
+                    &redactor.redact(question),
+                    &redactor.redact(&response.text),
+                    &response.model,
+                );
+                return save_session(&store, &session);
+            }
+            Err(e) if AiError::is_cancelled(&e) => {
+                print_cancelled();
+                return Ok(());
+            }
+            Err(e) if AiError::is_timeout(&e) || AiError::is_budget_exceeded(&e) => return Err(e),
+            Err(e) => {
+                // Whatever was printed so far may be incomplete; fall back to the buffered answer
+                println!(
+                    "\n\n{}\n",
+                    format!("Stream interrupted ({e}); fetching the full answer...").dimmed()
+                );
+            }
+        }
+    }
+
+    match client.answer_question(question, &history, &context).await {
+        Ok(answer) => {
+            println!("{}", answer.text);
+            println!();
+            if answer.model != client.primary_model() {
+                print_fallback_model(&answer.model);
+            }
+            if options.show_usage {
+                print_usage(&answer);
+            }
+            let redactor = client.redactor();
+            session.record(
+                &redactor.redact(question),
+                &redactor.redact(&answer.text),
+                &answer.model,
+            );
+            save_session(&store, &session)?;
+        }
+        Err(e) if AiError::is_cancelled(&e) => print_cancelled(),
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+fn resolve_session(
+    store: &SessionStore,
+    continue_last: bool,
+    session_name: Option<&str>,
+) -> Result<Session> {
+    if let Some(name) = session_name {
+        return Ok(store.load(name)?.unwrap_or_else(|| Session::new(name)));
+    }
+
+    if continue_last {
+        match store.last() {
+            Some(name) => {
+                if let Some(session) = store.load(&name)? {
+                    return Ok(session);
+                }
+            }
+            None => print_info("No previous session to continue; starting a new one"),
+        }
+    }
+
+    Ok(Session::new_unnamed())
+}
+
+fn save_session(store: &SessionStore, session: &Session) -> Result<()> {
+    store.save(session)?;
+    println!(
+        "{}",
+        format!(
+            "Session: {} (follow up with `aido ask --continue ...`)",
+            session.name
+        )
+        .dimmed()
+    );
     Ok(())
 }
@@ -1,19 +1,140 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::collections::VecDeque;
 
-use crate::ai::{extract_commands, ClaudeClient};
-use crate::config::load_config;
-use crate::executor::{confirm_execution, ConfirmAction, SafetyValidator, ShellExecutor};
-use crate::ui::display::{print_error, print_info};
+use crate::ai::client::AiResponse;
+use crate::ai::prompts::PromptKind;
+use crate::ai::{AiError, Alternative, ClaudeClient, ClientOptions, Plan, PlanStep, RiskHint};
+use crate::config::{load_config, AidoConfig};
+use crate::context::{with_attachments, Attachment, PromptContext};
+use crate::executor::shell::ExecutionResult;
+use crate::executor::validator::RiskLevel;
+use crate::executor::{
+    choose_alternative, confirm_execution, CommandHistory, ConfirmAction, HistoryRecord,
+    SafetyValidator, ShellExecutor,
+};
+use crate::modes::fix_mode::FailedCommand;
+use crate::modes::investigate::investigate;
+use crate::ui::display::{
+    print_attempts, print_cancelled, print_command_diff, print_context, print_error,
+    print_fallback_model, print_info, print_usage,
+};
+use crate::ui::input::edit_command;
 
-pub async fn do_mode(prompt: &str, skip_confirmation: bool, dry_run: bool) -> Result<()> {
+/// Stderr sent back to the model when asking for a revised command
+const RETRY_STDERR_MAX_BYTES: usize = 4 * 1024;
+/// Stderr kept in the history log for a failed command
+const HISTORY_STDERR_LINES: usize = 5;
+
+/// How generated commands are run
+#[derive(Debug, Clone, Default)]
+pub struct DoOptions {
+    /// Execute without asking for confirmation (`-y`)
+    pub skip_confirmation: bool,
+    /// Show what would be executed without running it (`-n`)
+    pub dry_run: bool,
+    /// Revised commands to try after a failure (`--retry-on-failure`)
+    pub retry_on_failure: u32,
+    /// Let the model run read-only probes before planning (`--investigate`)
+    pub investigate: bool,
+    /// Ask for this many approaches and let the user pick one (`--alternatives`)
+    pub alternatives: Option<u32>,
+}
+
+pub async fn do_mode(
+    prompt: &str,
+    attachments: &[Attachment],
+    do_options: &DoOptions,
+    options: &ClientOptions,
+) -> Result<()> {
     print_info(&format!("Generating command for: {}", prompt.italic()));
+    for attachment in attachments {
+        print_info(&format!("Attached {}", attachment.summary()));
+    }
 
     // Load config
     let config = load_config()?;
-    let unsafe_mode = config.execution.unsafe_mode;
+    print_unsafe_banner(&config);
+
+    // Create AI client
+    let client = ClaudeClient::from_config(&config, options)?;
+
+    let context =
+        PromptContext::for_prompt(&config.context, PromptKind::Do).redacted(client.redactor());
+    if config.ui.show_context {
+        print_context(&context);
+    }
+
+    // Generate command
+    let context = context.render();
+    let message = with_attachments(prompt, attachments);
+    let response = if do_options.investigate {
+        investigate(&client, &config, &message, &context, options).await
+    } else if let Some(count) = do_options.alternatives {
+        client
+            .generate_alternatives(&message, &context, count)
+            .await
+    } else {
+        client.generate_command(&message, &context).await
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) if AiError::is_cancelled(&e) => {
+            print_cancelled();
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    report_response(&client, &response, options);
+
+    let request = PlanRequest {
+        prompt,
+        message: &message,
+        context: &context,
+    };
+    if do_options.alternatives.is_some() {
+        if let Some(alternatives) = Alternative::parse_all(&response.text) {
+            let validator = SafetyValidator::new(config.execution.dangerous_commands.clone());
+            let risks: Vec<RiskLevel> = alternatives
+                .iter()
+                .map(|alternative| {
+                    alternative
+                        .plan
+                        .steps
+                        .iter()
+                        .map(|step| validator.validate(&step.command).risk_level)
+                        .max()
+                        .unwrap_or(RiskLevel::Low)
+                })
+                .collect();
 
-    if unsafe_mode {
+            let Some(choice) = choose_alternative(&alternatives, &risks)? else {
+                print_info("Command cancelled");
+                return Ok(());
+            };
+            let plan = alternatives[choice].plan.clone();
+            return execute_plan(&client, &config, &request, plan, do_options, options).await;
+        }
+        println!(
+            "{}",
+            "Response did not offer alternatives; treating it as a single plan.".dimmed()
+        );
+    }
+
+    run_plan(
+        &client,
+        &config,
+        &request,
+        &response.text,
+        do_options,
+        options,
+    )
+    .await
+}
+
+pub(crate) fn print_unsafe_banner(config: &AidoConfig) {
+    if config.execution.unsafe_mode {
         println!(
             "{}",
             "⚠ UNSAFE MODE: commands will run without validation or confirmation."
@@ -21,112 +142,370 @@ pub async fn do_mode(prompt: &str, skip_confirmation: bool, dry_run: bool) -> Re
                 .bold()
         );
     }
+}
 
-    // Create AI client
-    let client = ClaudeClient::from_config(&config)?;
+/// Fallback model, cache hit and `--usage` notes for a generated plan
+pub(crate) fn report_response(
+    client: &ClaudeClient,
+    response: &AiResponse,
+    options: &ClientOptions,
+) {
+    if response.model != client.primary_model() {
+        print_fallback_model(&response.model);
+    }
 
-    // Generate command
-    let response = client.generate_command(prompt).await?;
+    if response.cached {
+        println!(
+            "{} {}",
+            "⚡ Cached response".yellow().bold(),
+            "(use --no-cache to regenerate)".dimmed()
+        );
+    }
 
-    // Extract commands from response
-    let commands = extract_commands(&response);
+    if options.show_usage {
+        print_usage(response);
+    }
+}
+
+/// What a plan was generated from, kept for revising and refining its commands
+pub(crate) struct PlanRequest<'a> {
+    /// What the user asked for, as recorded in the command history
+    pub prompt: &'a str,
+    /// What the model was sent: the prompt plus any attachments
+    pub message: &'a str,
+    pub context: &'a str,
+}
 
-    if commands.is_empty() {
+/// Parse a DO response and run its plan. Shared by `aido do` and `aido fix`.
+pub(crate) async fn run_plan(
+    client: &ClaudeClient,
+    config: &AidoConfig,
+    request: &PlanRequest<'_>,
+    response_text: &str,
+    do_options: &DoOptions,
+    options: &ClientOptions,
+) -> Result<()> {
+    // Parse the plan, falling back to extracting commands from plain text
+    let plan = Plan::from_response(response_text);
+
+    if plan.is_empty() {
         print_error("Could not extract any commands from AI response");
         println!("\nAI Response:");
-        println!("{}", response);
+        println!("{}", response_text);
         return Ok(());
     }
 
-    // Create validator and executor
-    let validator = SafetyValidator::new(config.execution.dangerous_commands.clone());
-    let executor = ShellExecutor::new(dry_run || config.execution.dry_run);
-
-    // Process commands
-    for command in &commands {
-        let validation = validator.validate(command);
-
-        // Check if command is safe
-        if !validation.is_safe && !unsafe_mode {
-            print_error(&format!(
-                "Command blocked due to safety concerns: {}",
-                command
-            ));
-            if let Some(warning) = &validation.warning {
-                println!("{}", warning.red());
+    if plan.heuristic {
+        println!(
+            "{}",
+            "Response was not a structured plan; commands were extracted heuristically.".dimmed()
+        );
+    }
+
+    execute_plan(client, config, request, plan, do_options, options).await
+}
+
+/// Take each step of a parsed plan through validation, confirmation and execution
+async fn execute_plan(
+    client: &ClaudeClient,
+    config: &AidoConfig,
+    request: &PlanRequest<'_>,
+    plan: Plan,
+    do_options: &DoOptions,
+    options: &ClientOptions,
+) -> Result<()> {
+    let runner = StepRunner {
+        client,
+        config,
+        request,
+        validator: SafetyValidator::new(config.execution.dangerous_commands.clone()),
+        executor: ShellExecutor::new(do_options.dry_run || config.execution.dry_run),
+        do_options,
+        options,
+    };
+
+    for step in plan.steps {
+        runner.run_with_retries(step).await?;
+    }
+
+    Ok(())
+}
+
+/// A step that was executed
+struct StepRun {
+    result: ExecutionResult,
+    /// The user changed the command with Edit
+    edited: bool,
+}
+
+/// Everything needed to take one plan step from validation to execution
+struct StepRunner<'a> {
+    client: &'a ClaudeClient,
+    config: &'a AidoConfig,
+    request: &'a PlanRequest<'a>,
+    validator: SafetyValidator,
+    executor: ShellExecutor,
+    do_options: &'a DoOptions,
+    options: &'a ClientOptions,
+}
+
+impl StepRunner<'_> {
+    /// Run a step. When it fails and `--retry-on-failure` allows, ask for a
+    /// revised command and run that instead, up to the limit.
+    async fn run_with_retries(&self, step: PlanStep) -> Result<()> {
+        let max_attempts = self.do_options.retry_on_failure + 1;
+        let mut attempt = 1;
+        let mut transcript = Vec::new();
+        let mut pending = VecDeque::from([step]);
+
+        while let Some(mut step) = pending.pop_front() {
+            let revised = attempt > 1;
+            let Some(StepRun { result, edited }) = self.run_step(&mut step, revised).await? else {
+                continue;
+            };
+
+            let redactor = self.client.redactor();
+            let mut record = HistoryRecord::new(
+                &redactor.redact(self.request.prompt),
+                &redactor.redact(&step.command),
+                result.exit_code,
+                result.success,
+            );
+            record.attempt = attempt;
+            record.edited = edited;
+            if !result.success {
+                record.error = stderr_excerpt(&result.stderr).map(|text| redactor.redact(&text));
             }
-            continue;
-        } else if !validation.is_safe && unsafe_mode {
-            if let Some(warning) = &validation.warning {
-                println!("\n{} {}", "⚠".red().bold(), warning.red());
-            } else {
-                println!(
-                    "\n{} {}",
-                    "⚠".red().bold(),
-                    "Unsafe mode bypassing critical safety block.".red()
-                );
+            if !self.executor.is_dry_run() {
+                if let Err(e) = CommandHistory::open().and_then(|history| history.append(&record)) {
+                    tracing::warn!("Failed to record command history: {e}");
+                }
             }
-        }
+            transcript.push(record);
 
-        // Determine if we need confirmation
-        let needs_confirmation =
-            !unsafe_mode && (validation.requires_confirmation || config.execution.always_confirm);
+            if result.success || attempt == max_attempts {
+                continue;
+            }
 
-        // Skip confirmation if -y flag is set
-        let should_execute = if unsafe_mode || skip_confirmation || !needs_confirmation {
-            let marker = if unsafe_mode {
-                "⚠".red().bold().to_string()
-            } else {
-                "▶".cyan().bold().to_string()
+            attempt += 1;
+            println!(
+                "\n{} Attempt {} of {}: asking for a revised command...",
+                "↻".yellow().bold(),
+                attempt,
+                max_attempts
+            );
+            let failure = FailedCommand {
+                command: step.command.clone(),
+                exit_code: Some(result.exit_code),
+                stderr: Attachment::read(
+                    "stderr",
+                    result.stderr.as_bytes(),
+                    RETRY_STDERR_MAX_BYTES,
+                )
+                .ok()
+                .filter(|stderr| stderr.total_bytes > 0),
             };
-            println!("\n{} Auto-executing: {}", marker, command);
-            true
-        } else {
-            // Ask for confirmation
+            let prompt = format!("{}\n\n{}", self.request.message, failure.prompt());
+
+            let response = match self
+                .client
+                .generate_fix(&prompt, self.request.context)
+                .await
+            {
+                Ok(response) => response,
+                Err(e) if AiError::is_cancelled(&e) => {
+                    print_cancelled();
+                    break;
+                }
+                Err(e) => {
+                    print_error(&format!("Failed to get a revised command: {}", e));
+                    break;
+                }
+            };
+            report_response(self.client, &response, self.options);
+
+            let revision = Plan::from_response(&response.text);
+            if revision.is_empty() {
+                print_error("Could not extract a revised command from AI response");
+                break;
+            }
+            // The revision replaces whatever was left of the failed attempt
+            pending = revision.steps.into();
+        }
+
+        if transcript.len() > 1 {
+            print_attempts(&transcript);
+        }
+
+        Ok(())
+    }
+
+    /// Validate, confirm and execute one step. `None` when it wasn't run. An
+    /// edited or refined command replaces `step` and goes back through validation.
+    /// `revised` steps (retries after a failure) always ask for confirmation.
+    async fn run_step(&self, step: &mut PlanStep, revised: bool) -> Result<Option<StepRun>> {
+        let config = self.config;
+        let unsafe_mode = config.execution.unsafe_mode;
+        let mut refined = revised;
+        let mut edited = false;
+
+        loop {
+            let command = step.command.as_str();
+            let validation = self.validator.validate(command);
+
+            if !step.description.is_empty() {
+                println!("\n{} {}", "#".dimmed(), step.description.dimmed());
+            }
+
+            // Check if command is safe
+            if !validation.is_safe && !unsafe_mode {
+                print_error(&format!(
+                    "Command blocked due to safety concerns: {}",
+                    command
+                ));
+                if let Some(warning) = &validation.warning {
+                    println!("{}", warning.red());
+                }
+                return Ok(None);
+            } else if !validation.is_safe && unsafe_mode {
+                if let Some(warning) = &validation.warning {
+                    println!("\n{} {}", "⚠".red().bold(), warning.red());
+                } else {
+                    println!(
+                        "\n{} {}",
+                        "⚠".red().bold(),
+                        "Unsafe mode bypassing critical safety block.".red()
+                    );
+                }
+            }
+
+            // Determine if we need confirmation. A revised, refined or edited
+            // command is always shown again before it runs, even with -y.
+            let needs_confirmation = !unsafe_mode
+                && (refined
+                    || validation.requires_confirmation
+                    || step.risk_hint == RiskHint::High
+                    || config.execution.always_confirm);
+
+            // Skip confirmation if -y flag is set
+            let skip_confirmation = self.do_options.skip_confirmation && !refined;
+            if unsafe_mode || skip_confirmation || !needs_confirmation {
+                let marker = if unsafe_mode {
+                    "⚠".red().bold().to_string()
+                } else {
+                    "▶".cyan().bold().to_string()
+                };
+                println!("\n{} Auto-executing: {}", marker, command);
+                break;
+            }
+
+            // Ask for confirmation until the user executes or cancels
             match confirm_execution(command, &validation)? {
-                ConfirmAction::Execute => true,
+                ConfirmAction::Execute => break,
                 ConfirmAction::Cancel => {
                     print_info("Command cancelled");
-                    false
+                    return Ok(None);
                 }
-                ConfirmAction::Explain => {
-                    // Explain the command
-                    println!("\n{}", "Asking AI to explain the command...".dimmed());
-                    match client.explain_command(command).await {
-                        Ok(explanation) => {
-                            println!("\n{}", "═".repeat(60).blue());
-                            println!("{}", "Explanation".bold());
-                            println!("{}", "═".repeat(60).blue());
-                            println!("\n{}", explanation);
-                            println!();
-                        }
-                        Err(e) => {
-                            print_error(&format!("Failed to get explanation: {}", e));
-                        }
-                    }
-
-                    // Ask again if they want to execute after explanation
-                    match confirm_execution(command, &validation)? {
-                        ConfirmAction::Execute => true,
-                        _ => {
-                            print_info("Command cancelled");
-                            false
-                        }
+                ConfirmAction::Explain => self.explain(command).await,
+                ConfirmAction::Refine(instruction) => {
+                    if let Some(updated) = self.refine(step, &instruction).await {
+                        print_command_diff(&step.command, &updated.command);
+                        *step = updated;
+                        refined = true;
+                        edited = false;
                     }
                 }
                 ConfirmAction::Edit => {
-                    // TODO: Implement edit functionality
-                    print_info("Edit functionality not yet implemented");
-                    false
+                    let command = edit_command(&step.command)?;
+                    if command.is_empty() || command == step.command {
+                        print_info("Command unchanged");
+                    } else {
+                        print_command_diff(&step.command, &command);
+                        step.command = command;
+                        refined = true;
+                        edited = true;
+                    }
+                }
+            }
+        }
+
+        let result = if step.needs_tty {
+            self.executor.execute_interactive(&step.command)?
+        } else {
+            self.executor.execute(&step.command)?
+        };
+        Ok(Some(StepRun { result, edited }))
+    }
+
+    async fn explain(&self, command: &str) {
+        println!("\n{}", "Asking AI to explain the command...".dimmed());
+        let context = PromptContext::for_prompt(&self.config.context, PromptKind::Explain)
+            .redacted(self.client.redactor());
+        match self
+            .client
+            .explain_command(command, &context.render())
+            .await
+        {
+            Err(e) if AiError::is_cancelled(&e) => print_cancelled(),
+            Ok(explanation) => {
+                println!("\n{}", "═".repeat(60).blue());
+                println!("{}", "Explanation".bold());
+                println!("{}", "═".repeat(60).blue());
+                println!("\n{}", explanation.text);
+                println!();
+                if self.options.show_usage {
+                    print_usage(&explanation);
                 }
             }
+            Err(e) => {
+                print_error(&format!("Failed to get explanation: {}", e));
+            }
+        }
+    }
+
+    /// Ask the model to change `step` as instructed. `None` (after reporting why)
+    /// when there is no usable answer; the old command stays on offer.
+    async fn refine(&self, step: &PlanStep, instruction: &str) -> Option<PlanStep> {
+        println!("\n{}", "Refining the command...".dimmed());
+        let prompt = format!(
+            "{}\n\nCurrent command:\n{}\n\nChange requested: {}\n\nReply with the updated command as a plan with a single step.",
+            self.request.message, step.command, instruction
+        );
+
+        let response = match self
+            .client
+            .refine_command(&prompt, self.request.context)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) if AiError::is_cancelled(&e) => {
+                print_cancelled();
+                return None;
+            }
+            Err(e) => {
+                print_error(&format!("Failed to refine the command: {}", e));
+                return None;
+            }
         };
+        report_response(self.client, &response, self.options);
 
-        // Execute if approved
-        if should_execute {
-            executor.execute(command)?;
+        let plan = Plan::from_response(&response.text);
+        let Some(updated) = plan.steps.into_iter().next() else {
+            print_error("Could not extract a command from AI response");
+            return None;
+        };
+        if updated.command == step.command {
+            print_info("The command is unchanged");
         }
+        Some(updated)
     }
+}
 
-    Ok(())
+/// The last few lines of a failed command's stderr, for the history log
+fn stderr_excerpt(stderr: &str) -> Option<String> {
+    let lines: Vec<&str> = stderr.trim_end().lines().collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(lines[lines.len().saturating_sub(HISTORY_STDERR_LINES)..].join("\n"))
 }
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::env;
+
+use crate::ai::prompts::PromptKind;
+use crate::ai::{AiError, ClaudeClient, ClientOptions};
+use crate::config::load_config;
+use crate::context::{with_attachments, Attachment, PromptContext};
+use crate::modes::do_mode::{
+    print_unsafe_banner, report_response, run_plan, DoOptions, PlanRequest,
+};
+use crate::ui::display::{print_cancelled, print_context, print_info};
+
+/// Exported by the `aido setup-shell` hooks after every command
+const LAST_COMMAND_ENV: &str = "AIDO_LAST_COMMAND";
+const LAST_STATUS_ENV: &str = "AIDO_LAST_STATUS";
+
+/// A command that failed, with whatever is known about how it failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedCommand {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stderr: Option<Attachment>,
+}
+
+impl FailedCommand {
+    /// Combine explicit `--cmd`/`--exit-code`/`--stderr` values with the shell
+    /// hooks' environment variables and piped stdin, in that order of preference.
+    pub fn resolve(
+        command: Option<String>,
+        exit_code: Option<i32>,
+        stderr: Option<String>,
+        piped: Option<Attachment>,
+        max_bytes: usize,
+    ) -> Result<Self> {
+        let from_hooks = command.is_none();
+        let command = command
+            .or_else(|| env::var(LAST_COMMAND_ENV).ok())
+            .map(|command| command.trim().to_string())
+            // The hooks also see `aido fix` itself when it is run twice in a row
+            .filter(|command| !command.is_empty() && !command.starts_with("aido "))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No failed command to fix. Pass --cmd, or load the shell hooks with `eval \"$(aido setup-shell)\"`"
+                )
+            })?;
+
+        let exit_code = exit_code.or_else(|| {
+            from_hooks
+                .then(|| env::var(LAST_STATUS_ENV).ok())
+                .flatten()
+                .and_then(|status| status.trim().parse().ok())
+        });
+        if from_hooks && exit_code == Some(0) && stderr.is_none() && piped.is_none() {
+            anyhow::bail!("The last command (`{command}`) succeeded; nothing to fix");
+        }
+
+        let stderr = match stderr {
+            Some(text) => Some(Attachment::read("stderr", text.as_bytes(), max_bytes)?),
+            None => piped.map(|attachment| Attachment {
+                label: "stderr".to_string(),
+                ..attachment
+            }),
+        };
+
+        Ok(Self {
+            command,
+            exit_code,
+            stderr,
+        })
+    }
+
+    /// The DO request sent to the model
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!("This shell command failed:\n{}\n", self.command);
+        if let Some(code) = self.exit_code {
+            prompt.push_str(&format!("Exit code: {code}\n"));
+        }
+        prompt.push_str(
+            "Give the corrected command(s) that do what it was meant to do. If the error shows a missing prerequisite, add a step that sets it up first.",
+        );
+
+        with_attachments(&prompt, self.stderr.as_slice())
+    }
+}
+
+pub async fn fix_mode(
+    failure: &FailedCommand,
+    do_options: &DoOptions,
+    options: &ClientOptions,
+) -> Result<()> {
+    let status = failure
+        .exit_code
+        .map(|code| format!(" (exit code {code})"))
+        .unwrap_or_default();
+    print_info(&format!("Fixing: {}{}", failure.command.italic(), status));
+    match &failure.stderr {
+        Some(stderr) => print_info(&format!("Attached {}", stderr.summary())),
+        // The shell hooks only see the command line and its exit status
+        None => print_info(&format!(
+            "No error output attached, so the fix is a guess from the command alone. Paste the error with `aido fix --stderr \"...\"` or pipe it in: `{} 2>&1 | aido fix`",
+            failure.command
+        )),
+    }
+
+    let config = load_config()?;
+    print_unsafe_banner(&config);
+
+    let client = ClaudeClient::from_config(&config, options)?;
+
+    let context =
+        PromptContext::for_prompt(&config.context, PromptKind::Do).redacted(client.redactor());
+    if config.ui.show_context {
+        print_context(&context);
+    }
+
+    let request = format!("Fix the failed command: {}", failure.command);
+    let context = context.render();
+    let message = failure.prompt();
+    let response = match client.generate_fix(&message, &context).await {
+        Ok(response) => response,
+        Err(e) if AiError::is_cancelled(&e) => {
+            print_cancelled();
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    report_response(&client, &response, options);
+    let request = PlanRequest {
+        prompt: &request,
+        message: &message,
+        context: &context,
+    };
+    run_plan(
+        &client,
+        &config,
+        &request,
+        &response.text,
+        do_options,
+        options,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explicit_values_build_the_prompt() {
+        let failure = FailedCommand::resolve(
+            Some("cargo biuld".to_string()),
+            Some(101),
+            Some("error: no such command: `biuld`\n".to_string()),
+            None,
+            1024,
+        )
+        .unwrap();
+
+        assert_eq!(failure.exit_code, Some(101));
+        let prompt = failure.prompt();
+        assert!(prompt.starts_with("This shell command failed:\ncargo biuld\nExit code: 101\n"));
+        assert!(prompt
+            .contains("<<<BEGIN stderr>>>\nerror: no such command: `biuld`\n<<<END stderr>>>"));
+    }
+
+    #[test]
+    fn test_piped_input_becomes_stderr() {
+        let piped = Attachment::read("stdin", "permission denied\n".as_bytes(), 1024).unwrap();
+        let failure = FailedCommand::resolve(
+            Some("./deploy.sh".to_string()),
+            None,
+            None,
+            Some(piped),
+            1024,
+        )
+        .unwrap();
+
+        let stderr = failure.stderr.unwrap();
+        assert_eq!(stderr.label, "stderr");
+        assert_eq!(stderr.text, "permission denied\n");
+    }
+}
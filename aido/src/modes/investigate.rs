@@ -0,0 +1,138 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::time::Duration;
+
+use crate::ai::backend::Turn;
+use crate::ai::client::AiResponse;
+use crate::ai::{ClaudeClient, ClientOptions, ProbeRequest};
+use crate::config::AidoConfig;
+use crate::context::{with_attachments, Attachment};
+use crate::executor::{harden_probe, SafetyValidator, ShellExecutor};
+use crate::ui::display::print_usage;
+
+/// Output kept from each probe
+const PROBE_OUTPUT_MAX_BYTES: usize = 8 * 1024;
+/// Probes run per round; the prompt asks for no more than this
+const MAX_PROBES_PER_ROUND: usize = 5;
+/// How long one probe may run before it is killed
+const PROBE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// `aido do --investigate`: let the model run read-only probes before it plans,
+/// and return its final response. Probes are checked against the validator's
+/// probe policy and run without confirmation; refused ones are reported back.
+pub(crate) async fn investigate(
+    client: &ClaudeClient,
+    config: &AidoConfig,
+    prompt: &str,
+    context: &str,
+    options: &ClientOptions,
+) -> Result<AiResponse> {
+    let max_rounds = config.execution.max_probe_rounds;
+    let validator = SafetyValidator::new(config.execution.dangerous_commands.clone());
+    let executor = ShellExecutor::new(false);
+
+    let mut rounds: Vec<Turn> = Vec::new();
+    let mut results: Option<String> = None;
+    let mut over_limit = false;
+
+    loop {
+        let response = client
+            .investigate(prompt, context, &rounds, results.as_deref(), max_rounds)
+            .await?;
+        let Some(request) = ProbeRequest::parse(&response.text) else {
+            return Ok(response);
+        };
+        if over_limit {
+            anyhow::bail!(
+                "The model kept asking for probes after {} round(s) without producing a plan",
+                max_rounds
+            );
+        }
+        if options.show_usage {
+            print_usage(&response);
+        }
+
+        let round = rounds.len() as u32 + 1;
+        let message = if round > max_rounds {
+            over_limit = true;
+            "The probe limit has been reached and these probes were not run. Reply with the final plan now.".to_string()
+        } else {
+            println!(
+                "\n{} Investigating (round {} of {})",
+                "🔍".bold(),
+                round,
+                max_rounds
+            );
+            run_probes(&validator, &executor, &request.probes).await?
+        };
+
+        rounds.push(Turn {
+            user: results
+                .take()
+                .unwrap_or_else(|| format!("User request: {}", prompt)),
+            assistant: response.text,
+        });
+        results = Some(message);
+    }
+}
+
+/// Run one round of probes and describe the results for the model
+async fn run_probes(
+    validator: &SafetyValidator,
+    executor: &ShellExecutor,
+    probes: &[String],
+) -> Result<String> {
+    let mut notes = Vec::new();
+    let mut outputs = Vec::new();
+
+    if probes.len() > MAX_PROBES_PER_ROUND {
+        notes.push(format!(
+            "Only the first {} probes were run.",
+            MAX_PROBES_PER_ROUND
+        ));
+    }
+
+    for probe in probes.iter().take(MAX_PROBES_PER_ROUND) {
+        let validation = validator.validate_probe(probe);
+        if !validation.is_safe {
+            let reason = validation.warning.unwrap_or_default();
+            println!(
+                "  {} {} {}",
+                "✗".red(),
+                probe,
+                format!("({reason})").dimmed()
+            );
+            notes.push(format!("Not run: `{}` ({}).", probe, reason));
+            continue;
+        }
+
+        let result = executor
+            .capture(
+                &harden_probe(probe),
+                PROBE_OUTPUT_MAX_BYTES,
+                PROBE_TIME_LIMIT,
+            )
+            .await?;
+        let output = format!("{}{}", result.stdout, result.stderr);
+        let attachment = Attachment::read(
+            &format!("probe `{}` (exit {})", probe, result.exit_code),
+            output.as_bytes(),
+            PROBE_OUTPUT_MAX_BYTES,
+        )?;
+        println!(
+            "  {} {} {}",
+            "$".dimmed(),
+            probe,
+            format!(
+                "(exit {}, {} lines)",
+                result.exit_code,
+                output.lines().count()
+            )
+            .dimmed()
+        );
+        outputs.push(attachment);
+    }
+
+    notes.push("Ask for more probes or reply with the final plan.".to_string());
+    Ok(with_attachments(&notes.join("\n"), &outputs))
+}
@@ -1,5 +1,8 @@
 pub mod ask_mode;
 pub mod do_mode;
+pub mod fix_mode;
+mod investigate;
 
 pub use ask_mode::ask_mode;
-pub use do_mode::do_mode;
+pub use do_mode::{do_mode, DoOptions};
+pub use fix_mode::{fix_mode, FailedCommand};
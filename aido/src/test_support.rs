@@ -0,0 +1,11 @@
+//! Helpers shared by the unit tests
+
+use tempfile::TempDir;
+
+/// A fresh directory that is removed when dropped, even if the test panics
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("aido-test-")
+        .tempdir()
+        .expect("failed to create a temporary directory")
+}
@@ -1,15 +1,8 @@
+use crate::ai::client::AiResponse;
+use crate::context::PromptContext;
+use crate::executor::HistoryRecord;
 use colored::Colorize;
 
-pub fn format_output(text: &str) {
-    println!("{}", text);
-}
-
-pub fn print_header(title: &str) {
-    println!("\n{}", "═".repeat(60).blue());
-    println!("{}", title.bold());
-    println!("{}", "═".repeat(60).blue());
-}
-
 pub fn print_error(message: &str) {
     eprintln!("{} {}", "✗".red().bold(), message.red());
 }
@@ -21,3 +14,163 @@ pub fn print_success(message: &str) {
 pub fn print_info(message: &str) {
     println!("{} {}", "ℹ".blue().bold(), message);
 }
+
+pub fn print_fallback_model(model: &str) {
+    println!(
+        "{}",
+        format!("↪ Answered by fallback model {model}").dimmed()
+    );
+}
+
+pub fn print_cancelled() {
+    println!("\n{} {}", "✗".yellow().bold(), "Cancelled".yellow());
+}
+
+/// One dimmed line with the tokens and cost of a response (`--usage`)
+pub fn print_usage(response: &AiResponse) {
+    let line = match (&response.usage, response.cached) {
+        (_, true) => "Usage: none (cached response)".to_string(),
+        (None, false) => "Usage: not reported by this backend".to_string(),
+        (Some(usage), false) => {
+            let cost = usage
+                .cost_usd
+                .map(|cost| format!(", ${cost:.4}"))
+                .unwrap_or_default();
+            format!(
+                "Usage: {} input + {} output tokens{} ({})",
+                usage.input_tokens, usage.output_tokens, cost, response.model
+            )
+        }
+    };
+    println!("{}", line.dimmed());
+}
+
+/// The context about to be sent with a prompt, dimmed (`ui.show_context`)
+pub fn print_context(context: &PromptContext) {
+    if context.is_empty() {
+        return;
+    }
+
+    println!("{}", "Context:".dimmed());
+    for line in context.render().lines() {
+        println!("{}", format!("  {line}").dimmed());
+    }
+}
+
+/// Each attempt at a step that was retried after failing (`--retry-on-failure`)
+pub fn print_attempts(attempts: &[HistoryRecord]) {
+    println!("\n{}", "Attempts:".bold());
+    for record in attempts {
+        let status = if record.success {
+            "✓".green().to_string()
+        } else {
+            format!("{} exit {}", "✗".red(), record.exit_code)
+        };
+        println!("  {}. {} {}", record.attempt, record.command, status);
+        if let Some(error) = &record.error {
+            for line in error.lines() {
+                println!("     {}", line.dimmed());
+            }
+        }
+    }
+}
+
+/// The words of a refined command against the previous one: removed words in
+/// red and struck through, added words in green
+pub fn print_command_diff(old: &str, new: &str) {
+    let line: Vec<String> = diff_words(old, new)
+        .into_iter()
+        .map(|(change, word)| match change {
+            WordChange::Same => word.to_string(),
+            WordChange::Removed => word.red().strikethrough().to_string(),
+            WordChange::Added => word.green().bold().to_string(),
+        })
+        .collect();
+
+    println!("\n{} {}", "-".red(), old.red());
+    println!("{} {}", "+".green(), new.green());
+    println!("{} {}", "~".dimmed(), line.join(" "));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WordChange {
+    Same,
+    Removed,
+    Added,
+}
+
+/// Word-level diff via the longest common subsequence
+fn diff_words<'a>(old: &'a str, new: &'a str) -> Vec<(WordChange, &'a str)> {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+
+    // lcs[i][j]: common words between old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push((WordChange::Same, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push((WordChange::Removed, old[i]));
+            i += 1;
+        } else {
+            changes.push((WordChange::Added, new[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().map(|word| (WordChange::Removed, *word)));
+    changes.extend(new[j..].iter().map(|word| (WordChange::Added, *word)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_words() {
+        use WordChange::*;
+
+        assert_eq!(
+            diff_words(
+                "find . -name '*.log'",
+                "find . -name '*.log' -not -path './node_modules/*'"
+            ),
+            [
+                (Same, "find"),
+                (Same, "."),
+                (Same, "-name"),
+                (Same, "'*.log'"),
+                (Added, "-not"),
+                (Added, "-path"),
+                (Added, "'./node_modules/*'"),
+            ]
+        );
+        assert_eq!(
+            diff_words("rm build/out", "rm -r build/out"),
+            [(Same, "rm"), (Added, "-r"), (Same, "build/out")]
+        );
+        assert_eq!(
+            diff_words("ls -la", "du -sh"),
+            [
+                (Removed, "ls"),
+                (Removed, "-la"),
+                (Added, "du"),
+                (Added, "-sh")
+            ]
+        );
+    }
+}
@@ -1,5 +1,14 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use dialoguer::{theme::ColorfulTheme, Input};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Commands longer than this, or spanning several lines, are edited in $EDITOR
+const INLINE_EDIT_MAX_CHARS: usize = 80;
 
 pub fn prompt_user(prompt: &str) -> Result<String> {
     let input: String = Input::with_theme(&ColorfulTheme::default())
@@ -8,3 +17,151 @@ pub fn prompt_user(prompt: &str) -> Result<String> {
 
     Ok(input)
 }
+
+/// Let the user edit a command: on the prompt line for short ones, in $VISUAL or
+/// $EDITOR for long or multi-line ones. Returns the trimmed result, which is
+/// empty if everything was deleted.
+pub fn edit_command(command: &str) -> Result<String> {
+    if command.contains('\n') || command.chars().count() > INLINE_EDIT_MAX_CHARS {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vim".to_string());
+        return edit_in_editor(command, &editor);
+    }
+
+    let edited: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Edit command")
+        .with_initial_text(command)
+        .allow_empty(true)
+        .interact_text()?;
+
+    Ok(edited.trim().to_string())
+}
+
+/// Open `command` in `editor` (which may include arguments, e.g. `code --wait`)
+/// on a temporary file and read back the result
+fn edit_in_editor(command: &str, editor: &str) -> Result<String> {
+    let words = shell_words::split(editor)
+        .ok()
+        .filter(|words| !words.is_empty())
+        .with_context(|| format!("Invalid editor command: {}", editor))?;
+
+    // A fresh owner-only directory, so nobody else can swap the file for a
+    // symlink or read the command while it is being edited
+    let dir = env::temp_dir().join(format!(
+        "aido-edit-{}-{}",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    ));
+    create_private_dir(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join("command.sh");
+    if let Err(e) = write_new_private(&path, &format!("{}\n", command)) {
+        fs::remove_dir_all(&dir).ok();
+        return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+    }
+
+    let status = Command::new(&words[0])
+        .args(&words[1..])
+        .arg(&path)
+        .stdin(terminal_stdin())
+        .status()
+        .with_context(|| format!("Failed to start editor: {}", editor));
+    let edited = fs::read_to_string(&path);
+    fs::remove_dir_all(&dir).ok();
+
+    let status = status?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}", status);
+    }
+    Ok(edited
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .trim()
+        .to_string())
+}
+
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir(path)
+}
+
+/// Write a file that must not exist yet, readable only by the owner
+#[cfg(unix)]
+fn write_new_private(path: &Path, contents: &str) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_new_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Read one line from the keyboard. When stdin is a pipe (`... | aido do`) it
+/// has already been consumed, so the terminal is opened directly.
+pub fn read_terminal_line() -> Result<String> {
+    let mut line = String::new();
+    if io::stdin().is_terminal() {
+        io::stdin().read_line(&mut line)?;
+    } else {
+        BufReader::new(File::open("/dev/tty")?).read_line(&mut line)?;
+    }
+    Ok(line)
+}
+
+/// Stdin for child processes that need the keyboard (editors, pagers, prompts)
+pub fn terminal_stdin() -> Stdio {
+    if io::stdin().is_terminal() {
+        return Stdio::inherit();
+    }
+    File::open("/dev/tty")
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::inherit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_in_editor_reads_back_the_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // An "editor" that checks the file is private and replaces the command
+        let dir = temp_dir();
+        let editor = dir.path().join("editor.sh");
+        fs::write(
+            &editor,
+            "#!/bin/sh\n[ \"$(ls -l \"$1\" | cut -c1-10)\" = -rw------- ] || exit 1\nprintf '%s\\n' 'cargo build --locked' > \"$1\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let command = "docker run --rm -v \"$PWD\":/src -w /src rust:1 cargo build --release";
+        let edited = edit_in_editor(command, editor.to_str().unwrap());
+        assert_eq!(edited.unwrap(), "cargo build --locked");
+
+        assert!(edit_in_editor(command, "false").is_err());
+    }
+}